@@ -0,0 +1,505 @@
+use super::Audio;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path;
+
+enum Encoding {
+    BigEndian,
+    LittleEndian,
+    Float,
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_be_bytes([bytes[0], bytes[1]])
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+// aiff keeps its sample rate as an 80-bit extended float, because of course it does
+fn read_extended(bytes: &[u8]) -> f64 {
+    let exponent = (i32::from(bytes[0] & 0x7f) << 8) | i32::from(bytes[1]);
+    let mantissa = u64::from_be_bytes(bytes[2..10].try_into().unwrap());
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+    let sign = if bytes[0] & 0x80 == 0 { 1.0 } else { -1.0 };
+    sign * mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+fn write_extended(value: u32) -> [u8; 10] {
+    let mut bytes = [0; 10];
+    if value == 0 {
+        return bytes;
+    }
+    let mut exponent: u16 = 16383 + 63;
+    let mut mantissa = u64::from(value);
+    while mantissa & (1 << 63) == 0 {
+        mantissa <<= 1;
+        exponent -= 1;
+    }
+    bytes[0..2].copy_from_slice(&exponent.to_be_bytes());
+    bytes[2..10].copy_from_slice(&mantissa.to_be_bytes());
+    bytes
+}
+
+fn decode(data: &[u8], bits: u16, encoding: &Encoding) -> Result<Vec<f32>> {
+    let width = usize::from(bits.div_ceil(8));
+    if width == 0 || width > 4 {
+        return Err(invalid("unsupported aiff sample size"));
+    }
+    let scale = 2f32.powi(i32::from(bits) - 1);
+    let samples = data
+        .chunks_exact(width)
+        .map(|sample| match encoding {
+            Encoding::Float => f32::from_be_bytes(sample.try_into().unwrap()),
+            Encoding::BigEndian | Encoding::LittleEndian => {
+                let mut value: i32 = 0;
+                for index in 0..width {
+                    let byte = match encoding {
+                        Encoding::LittleEndian => sample[width - 1 - index],
+                        _ => sample[index],
+                    };
+                    value = (value << 8) | i32::from(byte);
+                }
+                // sign extend, then throw away any padding bits
+                let shift = 32 - width as u32 * 8;
+                let value = (value << shift) >> shift;
+                (value >> (width as u32 * 8 - u32::from(bits))) as f32 / scale
+            }
+        })
+        .collect();
+    Ok(samples)
+}
+
+// the sample size and how the samples are laid out, from the COMM chunk
+fn sample_format(common: &[u8], is_aifc: bool) -> Result<(u16, Encoding)> {
+    if common.len() < 18 {
+        return Err(invalid("aiff was too short"));
+    }
+    let encoding = if is_aifc && common.len() >= 22 {
+        match &common[18..22] {
+            b"NONE" | b"twos" => Encoding::BigEndian,
+            b"sowt" => Encoding::LittleEndian,
+            b"fl32" | b"FL32" => Encoding::Float,
+            _ => return Err(invalid("unsupported aiff compression")),
+        }
+    } else {
+        Encoding::BigEndian
+    };
+    let bits = match encoding {
+        Encoding::Float => 32,
+        _ => read_u16(&common[6..]),
+    };
+    Ok((bits, encoding))
+}
+
+fn encode(audio: &Audio) -> Vec<u8> {
+    if audio.float {
+        return audio
+            .samples
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes().to_vec())
+            .collect();
+    }
+    let width = usize::from(audio.bits.div_ceil(8));
+    audio
+        .samples
+        .iter()
+        .flat_map(|sample| {
+            let value = super::quantize(*sample, audio.bits) << (width as u32 * 8 - u32::from(audio.bits));
+            value.to_be_bytes()[4 - width..].to_vec()
+        })
+        .collect()
+}
+
+/// What's in an aiff, without reading all the audio
+#[derive(Clone, Copy)]
+pub struct Info {
+    pub channels: u16,
+    pub frames: usize,
+    pub bits: u16,
+    pub float: bool,
+    pub sample_rate: u32,
+}
+
+impl Info {
+    /// Whether the samples are laid out the same, however long each one is
+    pub fn same_format(&self, other: &Info) -> bool {
+        self.channels == other.channels
+            && self.bits == other.bits
+            && self.float == other.float
+            && self.sample_rate == other.sample_rate
+    }
+}
+
+pub fn info(file_path: &path::Path) -> Result<Info> {
+    let mut file = File::open(file_path)?;
+    let mut header = [0; 12];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != b"FORM" || !(&header[8..12] == b"AIFF" || &header[8..12] == b"AIFC") {
+        return Err(invalid("that's not an aiff"));
+    }
+
+    let mut chunk = [0; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let size = read_u32(&chunk[4..]);
+        if &chunk[0..4] == b"COMM" {
+            let mut common = [0; 22];
+            let length = (size as usize).min(common.len());
+            file.read_exact(&mut common[..length])?;
+            let float = &header[8..12] == b"AIFC" && matches!(&common[18..22], b"fl32" | b"FL32");
+            return Ok(Info {
+                channels: read_u16(&common[0..]),
+                frames: read_u32(&common[2..]) as usize,
+                bits: if float { 32 } else { read_u16(&common[6..]) },
+                float,
+                sample_rate: read_extended(&common[8..18]) as u32,
+            });
+        }
+        file.seek(SeekFrom::Current(i64::from(size + size % 2)))?;
+    }
+
+    Err(invalid("aiff had no COMM chunk"))
+}
+
+/// True as soon as there's a sample louder than `threshold` dBFS, reading the
+/// sound a bit at a time so a quiet start doesn't mean decoding the whole tape
+pub fn any_louder(file_path: &path::Path, threshold: f32) -> Result<bool> {
+    let threshold = 10f32.powf(threshold / 20.0);
+    let mut file = BufReader::new(File::open(file_path)?);
+    let mut header = [0; 12];
+    file.read_exact(&mut header)?;
+    let is_aifc = match (&header[0..4], &header[8..12]) {
+        (b"FORM", b"AIFF") => false,
+        (b"FORM", b"AIFC") => true,
+        _ => return Err(invalid("that's not an aiff")),
+    };
+
+    let mut format = None;
+    let mut chunk = [0; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let size = u64::from(read_u32(&chunk[4..]));
+        match &chunk[0..4] {
+            b"COMM" => {
+                let mut common = vec![0; size as usize];
+                file.read_exact(&mut common)?;
+                file.seek_relative((size % 2) as i64)?;
+                format = Some(sample_format(&common, is_aifc)?);
+            }
+            b"SSND" => {
+                let (bits, encoding) = format.ok_or_else(|| invalid("aiff had no COMM chunk"))?;
+                let mut offset = [0; 8];
+                file.read_exact(&mut offset)?;
+                let offset = read_u32(&offset);
+                file.seek_relative(i64::from(offset))?;
+                let width = usize::from(bits.div_ceil(8)).max(1);
+                let mut remaining = size.saturating_sub(8 + u64::from(offset)) as usize;
+                let mut buffer = vec![0; width * 65536];
+                while remaining >= width {
+                    let length = remaining.min(buffer.len()) / width * width;
+                    file.read_exact(&mut buffer[..length])?;
+                    let samples = decode(&buffer[..length], bits, &encoding)?;
+                    if samples.iter().any(|sample| sample.abs() > threshold) {
+                        return Ok(true);
+                    }
+                    remaining -= length;
+                }
+                return Ok(false);
+            }
+            _ => file.seek_relative((size + size % 2) as i64)?,
+        }
+    }
+
+    Err(invalid("aiff had no SSND chunk"))
+}
+
+/// The application chunk, without reading all the audio
+pub fn read_appl(file_path: &path::Path) -> Result<Option<Vec<u8>>> {
+    let mut file = File::open(file_path)?;
+    let mut header = [0; 12];
+    file.read_exact(&mut header)?;
+    if &header[0..4] != b"FORM" {
+        return Err(invalid("that's not an aiff"));
+    }
+
+    let mut chunk = [0; 8];
+    while file.read_exact(&mut chunk).is_ok() {
+        let size = read_u32(&chunk[4..]);
+        if &chunk[0..4] == b"APPL" {
+            let mut appl = vec![0; size as usize];
+            file.read_exact(&mut appl)?;
+            return Ok(Some(appl));
+        }
+        file.seek(SeekFrom::Current(i64::from(size + size % 2)))?;
+    }
+
+    Ok(None)
+}
+
+pub fn read(file_path: &path::Path) -> Result<Audio> {
+    let bytes = std::fs::read(file_path)?;
+    if bytes.len() < 12 || &bytes[0..4] != b"FORM" {
+        return Err(invalid("that's not an aiff"));
+    }
+    let is_aifc = match &bytes[8..12] {
+        b"AIFF" => false,
+        b"AIFC" => true,
+        _ => return Err(invalid("that's not an aiff")),
+    };
+
+    let mut common = None;
+    let mut sound = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let size = read_u32(&bytes[offset + 4..]) as usize;
+        let body = &bytes[offset + 8..bytes.len().min(offset + 8 + size)];
+        match id {
+            b"COMM" => common = Some(body),
+            b"SSND" => sound = Some(body),
+            _ => {}
+        }
+        offset += 8 + size + size % 2;
+    }
+
+    let common = common.ok_or_else(|| invalid("aiff had no COMM chunk"))?;
+    let sound = sound.ok_or_else(|| invalid("aiff had no SSND chunk"))?;
+    if common.len() < 18 || sound.len() < 8 {
+        return Err(invalid("aiff was too short"));
+    }
+
+    let channels = read_u16(&common[0..]);
+    let frames = read_u32(&common[2..]) as usize;
+    let sample_rate = read_extended(&common[8..18]) as u32;
+    let (bits, encoding) = sample_format(common, is_aifc)?;
+    let float = matches!(encoding, Encoding::Float);
+
+    let data_offset = 8 + read_u32(&sound[0..]) as usize;
+    let width = usize::from(bits.div_ceil(8));
+    let length = (frames * usize::from(channels) * width).min(sound.len().saturating_sub(data_offset));
+    let data = &sound[data_offset.min(sound.len())..data_offset.min(sound.len()) + length];
+
+    Ok(Audio {
+        channels,
+        sample_rate,
+        bits,
+        float,
+        samples: decode(data, bits, &encoding)?,
+    })
+}
+
+pub fn write(file_path: &path::Path, audio: &Audio) -> Result<()> {
+    write_with_appl(file_path, audio, None)
+}
+
+/// Write an aiff with an application chunk, which is where the op-1 keeps its
+/// patch settings
+pub fn write_with_appl(file_path: &path::Path, audio: &Audio, appl: Option<&[u8]>) -> Result<()> {
+    let data = encode(audio);
+    let form_type: &[u8] = if audio.float { b"AIFC" } else { b"AIFF" };
+
+    let mut common = vec![];
+    common.extend_from_slice(&audio.channels.to_be_bytes());
+    common.extend_from_slice(&(audio.frames() as u32).to_be_bytes());
+    common.extend_from_slice(&audio.bits.to_be_bytes());
+    common.extend_from_slice(&write_extended(audio.sample_rate));
+    if audio.float {
+        common.extend_from_slice(b"fl32");
+        // pascal string, padded to an even length
+        common.extend_from_slice(&[0, 0]);
+    }
+
+    let mut chunks = vec![];
+    if audio.float {
+        chunks.extend_from_slice(b"FVER");
+        chunks.extend_from_slice(&4u32.to_be_bytes());
+        chunks.extend_from_slice(&0xA280_5140u32.to_be_bytes());
+    }
+    chunks.extend_from_slice(b"COMM");
+    chunks.extend_from_slice(&(common.len() as u32).to_be_bytes());
+    chunks.extend_from_slice(&common);
+    if let Some(appl) = appl {
+        chunks.extend_from_slice(b"APPL");
+        chunks.extend_from_slice(&(appl.len() as u32).to_be_bytes());
+        chunks.extend_from_slice(appl);
+        if appl.len() % 2 == 1 {
+            chunks.push(0);
+        }
+    }
+    chunks.extend_from_slice(b"SSND");
+    chunks.extend_from_slice(&(data.len() as u32 + 8).to_be_bytes());
+    chunks.extend_from_slice(&[0; 8]);
+
+    let padding = data.len() % 2;
+    let form_size = 4 + chunks.len() + data.len() + padding;
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+    file.write_all(b"FORM")?;
+    file.write_all(&(form_size as u32).to_be_bytes())?;
+    file.write_all(form_type)?;
+    file.write_all(&chunks)?;
+    file.write_all(&data)?;
+    file.write_all(&vec![0; padding])?;
+    file.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{any_louder, info, read, read_appl, write, write_with_appl};
+    use crate::audio::Audio;
+    use std::fs;
+    use std::path;
+
+    fn temp(name: &str) -> path::PathBuf {
+        std::env::temp_dir().join(format!("save-op1-aiff-{}-{}.aif", std::process::id(), name))
+    }
+
+    fn audio(channels: u16, bits: u16, float: bool, samples: Vec<f32>) -> Audio {
+        Audio {
+            channels,
+            sample_rate: 44100,
+            bits,
+            float,
+            samples,
+        }
+    }
+
+    // near enough to survive being quantized to `bits`
+    fn assert_close(read: &[f32], written: &[f32], bits: u16) {
+        assert_eq!(read.len(), written.len());
+        let step = 2f32.powi(1 - i32::from(bits));
+        for (read, written) in read.iter().zip(written) {
+            assert!((read - written).abs() <= step, "{} isn't {}", read, written);
+        }
+    }
+
+    fn round_trip(name: &str, written: &Audio) -> Audio {
+        let file_path = temp(name);
+        write(&file_path, written).unwrap();
+        let read = read(&file_path).unwrap();
+        let info = info(&file_path).unwrap();
+        fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(read.channels, written.channels);
+        assert_eq!(read.sample_rate, 44100);
+        assert_eq!(read.bits, written.bits);
+        assert_eq!(read.float, written.float);
+        assert_eq!(info.channels, written.channels);
+        assert_eq!(info.frames, written.frames());
+        assert_eq!(info.bits, written.bits);
+        assert_eq!(info.float, written.float);
+        assert_eq!(info.sample_rate, 44100);
+        read
+    }
+
+    #[test]
+    fn sixteen_bit_round_trips() {
+        let written = audio(2, 16, false, vec![0.0, 0.5, -0.5, 0.25, -1.0, 0.999]);
+        assert_close(&round_trip("16", &written).samples, &written.samples, 16);
+    }
+
+    #[test]
+    fn twenty_four_bit_round_trips_with_an_odd_data_chunk() {
+        // one mono frame is three bytes, so the sound chunk needs padding
+        let written = audio(1, 24, false, vec![-0.123_456]);
+        assert_close(&round_trip("24", &written).samples, &written.samples, 24);
+
+        let written = audio(1, 24, false, vec![0.1, -0.2, 0.3]);
+        assert_close(&round_trip("24-odd", &written).samples, &written.samples, 24);
+    }
+
+    #[test]
+    fn float_round_trips_exactly() {
+        let written = audio(2, 32, true, vec![0.1, -0.2, 0.3, -0.4]);
+        assert_eq!(round_trip("fl32", &written).samples, written.samples);
+    }
+
+    #[test]
+    fn appl_round_trips_even_when_odd() {
+        let file_path = temp("appl");
+        let written = audio(1, 16, false, vec![0.5, -0.5]);
+        write_with_appl(&file_path, &written, Some(b"op-1{\"type\":\"drum\"}")).unwrap();
+        assert_eq!(read_appl(&file_path).unwrap().unwrap(), b"op-1{\"type\":\"drum\"}");
+        // the padding byte after the appl mustn't throw the sound chunk off
+        assert_close(&read(&file_path).unwrap().samples, &written.samples, 16);
+
+        write(&file_path, &written).unwrap();
+        assert_eq!(read_appl(&file_path).unwrap(), None);
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn any_louder_finds_the_loud_bit() {
+        let file_path = temp("louder");
+        let mut samples = vec![0.0; 200_000];
+        write(&file_path, &audio(1, 16, false, samples.clone())).unwrap();
+        assert!(!any_louder(&file_path, -60.0).unwrap());
+
+        // well past the first buffer's worth
+        samples[150_000] = 0.01;
+        write(&file_path, &audio(1, 16, false, samples)).unwrap();
+        assert!(any_louder(&file_path, -60.0).unwrap());
+        assert!(!any_louder(&file_path, -30.0).unwrap());
+
+        write(&file_path, &audio(2, 32, true, vec![0.0, 0.0, 0.0, -0.5])).unwrap();
+        assert!(any_louder(&file_path, -60.0).unwrap());
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn reads_little_endian_aifc_after_an_odd_chunk() {
+        let mut common = vec![];
+        common.extend_from_slice(&1u16.to_be_bytes());
+        common.extend_from_slice(&2u32.to_be_bytes());
+        common.extend_from_slice(&16u16.to_be_bytes());
+        common.extend_from_slice(&super::write_extended(44100));
+        common.extend_from_slice(b"sowt");
+        common.extend_from_slice(&[0, 0]);
+
+        let mut chunks = vec![];
+        // an odd sized chunk nobody cares about, and its padding byte
+        chunks.extend_from_slice(b"NAME");
+        chunks.extend_from_slice(&3u32.to_be_bytes());
+        chunks.extend_from_slice(b"pad\0");
+        chunks.extend_from_slice(b"COMM");
+        chunks.extend_from_slice(&(common.len() as u32).to_be_bytes());
+        chunks.extend_from_slice(&common);
+        chunks.extend_from_slice(b"SSND");
+        chunks.extend_from_slice(&12u32.to_be_bytes());
+        chunks.extend_from_slice(&[0; 8]);
+        chunks.extend_from_slice(&0x4000i16.to_le_bytes());
+        chunks.extend_from_slice(&(-0x4000i16).to_le_bytes());
+
+        let mut bytes = b"FORM".to_vec();
+        bytes.extend_from_slice(&(4 + chunks.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(b"AIFC");
+        bytes.extend_from_slice(&chunks);
+        let file_path = temp("sowt");
+        fs::write(&file_path, bytes).unwrap();
+
+        let audio = read(&file_path).unwrap();
+        assert_eq!(audio.samples, vec![0.5, -0.5]);
+        let info = info(&file_path).unwrap();
+        assert_eq!((info.channels, info.frames, info.bits), (1, 2, 16));
+        assert!(any_louder(&file_path, -10.0).unwrap());
+        assert!(!any_louder(&file_path, -3.0).unwrap());
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    #[test]
+    fn refuses_things_that_arent_aiffs() {
+        let file_path = temp("not");
+        fs::write(&file_path, b"RIFF\0\0\0\0WAVE").unwrap();
+        assert!(read(&file_path).is_err());
+        assert!(info(&file_path).is_err());
+        assert!(any_louder(&file_path, -60.0).is_err());
+        fs::remove_file(&file_path).unwrap();
+    }
+}
@@ -0,0 +1,246 @@
+use super::Audio;
+
+/// Anything quieter than this, in dBFS, counts as silence
+pub const SILENCE: f32 = -60.0;
+
+/// The first and last frame louder than `threshold` dBFS, if there are any
+pub fn bounds(audio: &Audio, threshold: f32) -> Option<(usize, usize)> {
+    let threshold = 10f32.powf(threshold / 20.0);
+    let channels = usize::from(audio.channels.max(1));
+    let loud = |frame: &[f32]| frame.iter().any(|sample| sample.abs() > threshold);
+    let start = audio.samples.chunks(channels).position(loud)?;
+    let end = audio.samples.chunks(channels).rposition(loud)?;
+    Some((start, end + 1))
+}
+
+/// Frames `start` up to `end`, as a new piece of audio
+pub fn slice(audio: &Audio, start: usize, end: usize) -> Audio {
+    let channels = usize::from(audio.channels);
+    let end = end.min(audio.frames());
+    let start = start.min(end);
+    Audio {
+        samples: audio.samples[start * channels..end * channels].to_vec(),
+        ..audio.clone()
+    }
+}
+
+/// Linear fades, lengths in seconds
+pub fn fade(audio: &mut Audio, fade_in: f64, fade_out: f64) {
+    let frames = audio.frames();
+    let channels = usize::from(audio.channels);
+    let fade_in = ((fade_in * f64::from(audio.sample_rate)) as usize).min(frames);
+    let fade_out = ((fade_out * f64::from(audio.sample_rate)) as usize).min(frames);
+    for (frame, samples) in audio.samples.chunks_mut(channels).enumerate() {
+        let mut level = 1.0;
+        if frame < fade_in {
+            level *= frame as f32 / fade_in as f32;
+        }
+        if frames - frame <= fade_out {
+            level *= (frames - frame - 1) as f32 / fade_out as f32;
+        }
+        for sample in samples.iter_mut() {
+            *sample *= level;
+        }
+    }
+}
+
+/// The stretches of sound between gaps of at least `gap` seconds of silence
+pub fn pieces(audio: &Audio, threshold: f32, gap: f64) -> Vec<(usize, usize)> {
+    let threshold = 10f32.powf(threshold / 20.0);
+    let gap = (gap * f64::from(audio.sample_rate)) as usize;
+    let channels = usize::from(audio.channels.max(1));
+
+    let mut pieces = vec![];
+    let mut start = None;
+    let mut last_loud = 0;
+    for (frame, samples) in audio.samples.chunks(channels).enumerate() {
+        if !samples.iter().any(|sample| sample.abs() > threshold) {
+            continue;
+        }
+        match start {
+            None => start = Some(frame),
+            Some(piece_start) if frame - last_loud > gap => {
+                pieces.push((piece_start, last_loud + 1));
+                start = Some(frame);
+            }
+            Some(_) => {}
+        }
+        last_loud = frame;
+    }
+    if let Some(piece_start) = start {
+        pieces.push((piece_start, last_loud + 1));
+    }
+    pieces
+}
+
+/// The pieces either side of some cut points, given in seconds
+pub fn cut(audio: &Audio, cuts: &[f64]) -> Vec<(usize, usize)> {
+    let mut points: Vec<usize> = cuts
+        .iter()
+        .map(|seconds| ((seconds * f64::from(audio.sample_rate)) as usize).min(audio.frames()))
+        .collect();
+    points.sort_unstable();
+    points.insert(0, 0);
+    points.push(audio.frames());
+    points.dedup();
+    points.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+/// `count` pieces of the same length, end to end, with the last one taking
+/// whatever's left over
+pub fn even(audio: &Audio, count: usize) -> Vec<(usize, usize)> {
+    let frames = audio.frames();
+    let length = frames / count.max(1);
+    (0..count)
+        .map(|index| {
+            let end = if index + 1 == count { frames } else { (index + 1) * length };
+            (index * length, end)
+        })
+        .filter(|(start, end)| end > start)
+        .collect()
+}
+
+/// Pieces end to end, as long as they're all the same channels and rate
+pub fn join(pieces: &[Audio]) -> Option<Audio> {
+    let first = pieces.first()?;
+    if pieces
+        .iter()
+        .any(|piece| piece.channels != first.channels || piece.sample_rate != first.sample_rate)
+    {
+        return None;
+    }
+    Some(Audio {
+        samples: pieces.iter().flat_map(|piece| piece.samples.iter().copied()).collect(),
+        bits: pieces.iter().map(|piece| piece.bits).max().unwrap_or(first.bits),
+        float: pieces.iter().any(|piece| piece.float),
+        ..first.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bounds, cut, even, fade, join, pieces, slice, SILENCE};
+    use crate::audio::Audio;
+
+    fn stereo(frames: &[f32]) -> Audio {
+        Audio {
+            channels: 2,
+            sample_rate: 10,
+            bits: 16,
+            float: false,
+            samples: frames.iter().flat_map(|sample| [*sample, *sample]).collect(),
+        }
+    }
+
+    #[test]
+    fn bounds_skip_the_quiet_ends() {
+        let audio = stereo(&[0.0, 0.0001, 0.5, 0.0, -0.5, 0.0]);
+        assert_eq!(bounds(&audio, SILENCE), Some((2, 5)));
+    }
+
+    #[test]
+    fn bounds_see_either_channel() {
+        let mut audio = stereo(&[0.0, 0.0, 0.0]);
+        audio.samples[3] = 0.5;
+        assert_eq!(bounds(&audio, SILENCE), Some((1, 2)));
+    }
+
+    #[test]
+    fn all_silent_has_no_bounds() {
+        assert_eq!(bounds(&stereo(&[0.0, 0.0001, -0.0001]), SILENCE), None);
+        assert_eq!(bounds(&stereo(&[]), SILENCE), None);
+    }
+
+    #[test]
+    fn slice_clamps_to_the_audio() {
+        let audio = stereo(&[0.1, 0.2, 0.3, 0.4]);
+        assert_eq!(slice(&audio, 1, 3).samples, vec![0.2, 0.2, 0.3, 0.3]);
+        assert_eq!(slice(&audio, 2, 10).frames(), 2);
+        assert_eq!(slice(&audio, 6, 10).frames(), 0);
+        assert_eq!(slice(&audio, 3, 1).frames(), 0);
+    }
+
+    #[test]
+    fn fades_go_from_and_to_nothing() {
+        // half a second each way at 10 frames a second
+        let mut audio = stereo(&[1.0; 10]);
+        fade(&mut audio, 0.5, 0.5);
+        let left: Vec<f32> = audio.samples.iter().step_by(2).copied().collect();
+        assert_eq!(left, vec![0.0, 0.2, 0.4, 0.6, 0.8, 0.8, 0.6, 0.4, 0.2, 0.0]);
+        assert_eq!(audio.samples[3], audio.samples[2]);
+    }
+
+    #[test]
+    fn fades_longer_than_the_audio_stay_in_range() {
+        let mut audio = stereo(&[1.0; 4]);
+        fade(&mut audio, 10.0, 10.0);
+        assert!(audio.samples.iter().all(|sample| (0.0..=1.0).contains(sample)));
+        assert_eq!(audio.samples[0], 0.0);
+        assert_eq!(audio.samples[6], 0.0);
+
+        let mut empty = stereo(&[]);
+        fade(&mut empty, 1.0, 1.0);
+        assert!(empty.samples.is_empty());
+    }
+
+    #[test]
+    fn pieces_split_at_long_enough_gaps() {
+        // one frame of silence is a tenth of a second
+        let audio = stereo(&[0.0, 0.5, 0.0, 0.5, 0.0, 0.0, 0.0, 0.5, 0.0]);
+        assert_eq!(pieces(&audio, SILENCE, 0.2), vec![(1, 4), (7, 8)]);
+        assert_eq!(pieces(&audio, SILENCE, 0.0), vec![(1, 2), (3, 4), (7, 8)]);
+        assert_eq!(pieces(&audio, SILENCE, 10.0), vec![(1, 8)]);
+    }
+
+    #[test]
+    fn pieces_reach_the_ends_and_skip_silence() {
+        let audio = stereo(&[0.5, 0.0, 0.0, 0.0, 0.5]);
+        assert_eq!(pieces(&audio, SILENCE, 0.2), vec![(0, 1), (4, 5)]);
+        assert!(pieces(&stereo(&[0.0; 5]), SILENCE, 0.2).is_empty());
+        assert!(pieces(&stereo(&[]), SILENCE, 0.2).is_empty());
+    }
+
+    #[test]
+    fn cuts_never_make_empty_pieces() {
+        let audio = stereo(&[0.5; 10]);
+        assert_eq!(cut(&audio, &[0.4]), vec![(0, 4), (4, 10)]);
+        // out of order, doubled, at the ends and past the end
+        assert_eq!(
+            cut(&audio, &[0.7, 0.0, 0.4, 0.4, 1.0, 5.0]),
+            vec![(0, 4), (4, 7), (7, 10)]
+        );
+        assert_eq!(cut(&audio, &[]), vec![(0, 10)]);
+        assert!(cut(&stereo(&[]), &[1.0]).is_empty());
+    }
+
+    #[test]
+    fn even_pieces_cover_every_frame() {
+        let audio = stereo(&[0.5; 10]);
+        assert_eq!(even(&audio, 2), vec![(0, 5), (5, 10)]);
+        assert_eq!(even(&audio, 3), vec![(0, 3), (3, 6), (6, 10)]);
+        assert_eq!(even(&audio, 1), vec![(0, 10)]);
+        assert!(even(&audio, 0).is_empty());
+        // more pieces than frames leaves only the one with everything in it
+        assert_eq!(even(&stereo(&[0.5; 3]), 5), vec![(0, 3)]);
+    }
+
+    #[test]
+    fn join_needs_matching_pieces() {
+        assert!(join(&[]).is_none());
+
+        let mut wider = stereo(&[0.3]);
+        wider.bits = 24;
+        let joined = join(&[stereo(&[0.1, 0.2]), stereo(&[]), wider]).unwrap();
+        assert_eq!(joined.samples, vec![0.1, 0.1, 0.2, 0.2, 0.3, 0.3]);
+        assert_eq!(joined.bits, 24);
+
+        let mut faster = stereo(&[0.3]);
+        faster.sample_rate = 20;
+        assert!(join(&[stereo(&[0.1]), faster]).is_none());
+        let mono = Audio {
+            channels: 1,
+            ..stereo(&[0.3])
+        };
+        assert!(join(&[stereo(&[0.1]), mono]).is_none());
+    }
+}
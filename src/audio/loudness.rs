@@ -0,0 +1,257 @@
+use super::Audio;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+/// EBU R128 measurements, loudness in LUFS, range in LU and peak in dBTP
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Loudness {
+    pub integrated: f64,
+    pub range: f64,
+    pub true_peak: f64,
+}
+
+impl std::fmt::Display for Loudness {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1} LUFS, {:.1} dBTP, LRA {:.1} LU",
+            self.integrated, self.true_peak, self.range
+        )
+    }
+}
+
+impl Loudness {
+    /// The gain to hit `target`, held back so the true peak stays under -1dBTP
+    pub fn gain_to(&self, target: f64) -> f64 {
+        (target - self.integrated).min(-1.0 - self.true_peak)
+    }
+}
+
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Biquad {
+        Biquad {
+            b,
+            a,
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[1] * self.y[0]
+            - self.a[2] * self.y[1];
+        self.x = [input, self.x[0]];
+        self.y = [output, self.y[0]];
+        output
+    }
+}
+
+// the two stage k-weighting from BS.1770, worked out for any sample rate
+fn k_weighting(sample_rate: f64) -> (Biquad, Biquad) {
+    let f0 = 1681.974450955533;
+    let gain = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    );
+
+    (shelf, high_pass)
+}
+
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(1e-20).log10()
+}
+
+fn mean(powers: &[f64]) -> f64 {
+    powers.iter().sum::<f64>() / powers.len().max(1) as f64
+}
+
+/// Mean square of the k-weighted signal in 100ms steps, summed over channels
+fn segments(audio: &Audio) -> Vec<f64> {
+    let step = (audio.sample_rate / 10).max(1) as usize;
+    let mut segments = vec![0.0; audio.frames() / step];
+    for channel in 0..audio.channels {
+        let (mut shelf, mut high_pass) = k_weighting(f64::from(audio.sample_rate));
+        for (index, segment) in segments.iter_mut().enumerate() {
+            let mut sum = 0.0;
+            for frame in index * step..(index + 1) * step {
+                let sample = high_pass.process(shelf.process(f64::from(audio.sample(frame, channel))));
+                sum += sample * sample;
+            }
+            *segment += sum / step as f64;
+        }
+    }
+    segments
+}
+
+/// Windows of `length` segments, one starting at every segment
+fn windows(segments: &[f64], length: usize) -> Vec<f64> {
+    if segments.len() < length {
+        return vec![];
+    }
+    segments
+        .windows(length)
+        .map(|window| window.iter().sum::<f64>() / length as f64)
+        .collect()
+}
+
+fn integrated(segments: &[f64]) -> f64 {
+    let blocks: Vec<f64> = windows(segments, 4)
+        .into_iter()
+        .filter(|power| to_lufs(*power) > -70.0)
+        .collect();
+    let gate = to_lufs(mean(&blocks)) - 10.0;
+    let gated: Vec<f64> = blocks
+        .into_iter()
+        .filter(|power| to_lufs(*power) > gate)
+        .collect();
+    if gated.is_empty() {
+        return -70.0;
+    }
+    to_lufs(mean(&gated))
+}
+
+fn range(segments: &[f64]) -> f64 {
+    let blocks: Vec<f64> = windows(segments, 30)
+        .into_iter()
+        .filter(|power| to_lufs(*power) > -70.0)
+        .collect();
+    let gate = to_lufs(mean(&blocks)) - 20.0;
+    let mut gated: Vec<f64> = blocks
+        .into_iter()
+        .map(to_lufs)
+        .filter(|lufs| *lufs > gate)
+        .collect();
+    if gated.is_empty() {
+        return 0.0;
+    }
+    gated.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let percentile = |p: f64| gated[((gated.len() - 1) as f64 * p).round() as usize];
+    percentile(0.95) - percentile(0.10)
+}
+
+// four times oversampled with a windowed sinc, which is what BS.1770 asks for
+fn true_peak(audio: &Audio) -> f64 {
+    const FACTOR: usize = 4;
+    const TAPS: usize = 12;
+    let phases: Vec<Vec<f64>> = (0..FACTOR)
+        .map(|phase| {
+            (0..TAPS)
+                .map(|tap| {
+                    let t = tap as f64 - (TAPS / 2) as f64 + 1.0 - phase as f64 / FACTOR as f64;
+                    let sinc = if t == 0.0 { 1.0 } else { (PI * t).sin() / (PI * t) };
+                    let window = 0.5 + 0.5 * (PI * t / (TAPS / 2) as f64).cos();
+                    sinc * window
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut peak: f64 = 0.0;
+    for channel in 0..audio.channels {
+        let mut history = [0.0; TAPS];
+        for frame in 0..audio.frames() {
+            history.rotate_right(1);
+            history[0] = f64::from(audio.sample(frame, channel));
+            for phase in &phases {
+                let value: f64 = phase.iter().zip(&history).map(|(tap, x)| tap * x).sum();
+                peak = peak.max(value.abs());
+            }
+        }
+    }
+    20.0 * peak.max(1e-10).log10()
+}
+
+pub fn measure(audio: &Audio) -> Loudness {
+    let segments = segments(audio);
+    Loudness {
+        integrated: integrated(&segments),
+        range: range(&segments),
+        true_peak: true_peak(audio),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::measure;
+    use crate::audio::Audio;
+    use std::f32::consts::TAU;
+
+    // a stereo 1kHz sine at 48kHz, with the peak at `db` dBFS
+    fn sine(db: f32, seconds: usize) -> Vec<f32> {
+        let amplitude = 10f32.powf(db / 20.0);
+        (0..48000 * seconds)
+            .flat_map(|frame| {
+                let sample = amplitude * (TAU * 1000.0 * frame as f32 / 48000.0).sin();
+                [sample, sample]
+            })
+            .collect()
+    }
+
+    fn stereo(samples: Vec<f32>) -> Audio {
+        Audio {
+            channels: 2,
+            sample_rate: 48000,
+            bits: 24,
+            float: false,
+            samples,
+        }
+    }
+
+    #[test]
+    fn a_sine_at_minus_23_reads_minus_23() {
+        let loudness = measure(&stereo(sine(-23.0, 10)));
+        assert!((loudness.integrated + 23.0).abs() < 0.1, "{}", loudness);
+        assert!(loudness.range.abs() < 0.1, "{}", loudness);
+        assert!((loudness.true_peak + 23.0).abs() < 0.1, "{}", loudness);
+    }
+
+    #[test]
+    fn silence_is_gated_out() {
+        let loudness = measure(&stereo(vec![0.0; 48000 * 2 * 5]));
+        assert_eq!(loudness.integrated, -70.0);
+        assert_eq!(loudness.range, 0.0);
+    }
+
+    #[test]
+    fn quiet_stretches_dont_pull_the_integrated_down() {
+        let mut samples = sine(-23.0, 10);
+        samples.extend(sine(-90.0, 10));
+        let loudness = measure(&stereo(samples));
+        assert!((loudness.integrated + 23.0).abs() < 0.1, "{}", loudness);
+    }
+
+    #[test]
+    fn range_spans_the_loud_and_quiet_parts() {
+        let mut samples = sine(-20.0, 20);
+        samples.extend(sine(-30.0, 20));
+        let loudness = measure(&stereo(samples));
+        assert!((loudness.range - 10.0).abs() < 1.0, "{}", loudness);
+    }
+}
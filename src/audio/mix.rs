@@ -0,0 +1,127 @@
+use super::Audio;
+use std::f32::consts::FRAC_PI_4;
+
+#[derive(Clone, Copy)]
+pub struct TrackMix {
+    /// gain in dB
+    pub gain: f32,
+    /// -1.0 is hard left, 1.0 is hard right
+    pub pan: f32,
+}
+
+impl Default for TrackMix {
+    fn default() -> TrackMix {
+        TrackMix {
+            gain: 0.0,
+            pan: 0.0,
+        }
+    }
+}
+
+impl TrackMix {
+    // constant power, so a centred track sits at -3dB in each ear
+    fn levels(&self) -> (f32, f32) {
+        let gain = 10f32.powf(self.gain / 20.0);
+        let angle = (self.pan.clamp(-1.0, 1.0) + 1.0) * FRAC_PI_4;
+        (gain * angle.cos(), gain * angle.sin())
+    }
+}
+
+/// Sum some tracks into one stereo track, as long as the longest of them
+pub fn mixdown(tracks: &[(Audio, TrackMix)]) -> Audio {
+    let frames = tracks.iter().map(|(audio, _)| audio.frames()).max().unwrap_or(0);
+    let sample_rate = tracks.first().map_or(44100, |(audio, _)| audio.sample_rate);
+    let bits = tracks.first().map_or(16, |(audio, _)| audio.bits);
+    let float = tracks.iter().any(|(audio, _)| audio.float);
+
+    let mut samples = vec![0.0; frames * 2];
+    for (audio, mix) in tracks {
+        let (left, right) = mix.levels();
+        for frame in 0..audio.frames() {
+            // a stereo track keeps its own image and the pan acts as balance
+            samples[frame * 2] += audio.sample(frame, 0) * left;
+            samples[frame * 2 + 1] += audio.sample(frame, 1) * right;
+        }
+    }
+
+    for sample in samples.iter_mut() {
+        *sample = sample.clamp(-1.0, 1.0);
+    }
+
+    Audio {
+        channels: 2,
+        sample_rate,
+        bits: if float { 32 } else { bits },
+        float,
+        samples,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mixdown, TrackMix};
+    use crate::audio::Audio;
+
+    fn constant(value: f32, frames: usize) -> Audio {
+        Audio {
+            channels: 1,
+            sample_rate: 44100,
+            bits: 16,
+            float: false,
+            samples: vec![value; frames],
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn centre_is_constant_power() {
+        let mix = mixdown(&[(constant(0.5, 4), TrackMix::default())]);
+        assert_eq!(mix.channels, 2);
+        assert!(close(mix.samples[0], 0.5 * std::f32::consts::FRAC_1_SQRT_2));
+        assert!(close(mix.samples[0], mix.samples[1]));
+        let power = mix.samples[0].powi(2) + mix.samples[1].powi(2);
+        assert!(close(power, 0.25));
+    }
+
+    #[test]
+    fn hard_pans_go_to_one_side() {
+        let left = TrackMix { gain: 0.0, pan: -1.0 };
+        let mix = mixdown(&[(constant(0.5, 4), left)]);
+        assert!(close(mix.samples[0], 0.5));
+        assert!(close(mix.samples[1], 0.0));
+
+        // past hard right is still hard right
+        let right = TrackMix { gain: 0.0, pan: 3.0 };
+        let mix = mixdown(&[(constant(0.5, 4), right)]);
+        assert!(close(mix.samples[0], 0.0));
+        assert!(close(mix.samples[1], 0.5));
+    }
+
+    #[test]
+    fn gain_is_in_db_and_the_sum_clips() {
+        let quieter = TrackMix { gain: -6.0, pan: -1.0 };
+        let mix = mixdown(&[(constant(1.0, 4), quieter)]);
+        assert!(close(mix.samples[0], 10f32.powf(-6.0 / 20.0)));
+
+        let loud = TrackMix { gain: 12.0, pan: 0.0 };
+        let mix = mixdown(&[(constant(0.5, 4), loud), (constant(-0.9, 2), TrackMix::default())]);
+        assert!(mix.samples.iter().all(|sample| (-1.0..=1.0).contains(sample)));
+        // 0.5 at +12dB is about 1.41 in each ear once the quieter track stops
+        assert_eq!(mix.samples[4], 1.0);
+        assert!(mix.samples[0] < 1.0);
+    }
+
+    #[test]
+    fn as_long_as_the_longest_track() {
+        let mix = mixdown(&[
+            (constant(0.5, 2), TrackMix::default()),
+            (constant(0.5, 6), TrackMix::default()),
+        ]);
+        assert_eq!(mix.frames(), 6);
+        assert!(close(mix.samples[4], 0.5 * std::f32::consts::FRAC_1_SQRT_2));
+        assert!(close(mix.samples[0], std::f32::consts::FRAC_1_SQRT_2));
+    }
+}
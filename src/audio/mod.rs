@@ -0,0 +1,105 @@
+use std::io::Result;
+use std::path;
+
+pub mod aiff;
+pub mod edit;
+pub mod loudness;
+pub mod mix;
+pub mod wav;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Aiff,
+    Wav,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Aiff => "aif",
+            Format::Wav => "wav",
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.extension())
+    }
+}
+
+/// Decoded audio, with the samples interleaved and scaled to -1.0..1.0
+#[derive(Clone)]
+pub struct Audio {
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub bits: u16,
+    pub float: bool,
+    pub samples: Vec<f32>,
+}
+
+impl Audio {
+    pub fn read(file_path: &path::Path) -> Result<Audio> {
+        aiff::read(file_path)
+    }
+
+    pub fn write(&self, file_path: &path::Path, format: Format) -> Result<()> {
+        match format {
+            Format::Aiff => aiff::write(file_path, self),
+            Format::Wav => wav::write(file_path, self),
+        }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.samples.len() / usize::from(self.channels.max(1))
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.frames() as f64 / f64::from(self.sample_rate.max(1))
+    }
+
+    /// Every frame averaged down to one channel
+    pub fn mono(&self) -> Vec<f32> {
+        let channels = usize::from(self.channels.max(1));
+        self.samples
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect()
+    }
+
+    /// Pad with silence out to `frames`, so tracks line up from the start
+    pub fn pad_to(&mut self, frames: usize) {
+        let length = frames * usize::from(self.channels);
+        if self.samples.len() < length {
+            self.samples.resize(length, 0.0);
+        }
+    }
+
+    /// The sample at `frame` on `channel`, or silence past the end
+    pub fn sample(&self, frame: usize, channel: u16) -> f32 {
+        let channel = channel.min(self.channels.saturating_sub(1));
+        self.samples
+            .get(frame * usize::from(self.channels) + usize::from(channel))
+            .copied()
+            .unwrap_or(0.0)
+    }
+}
+
+/// Seconds of silence at the start and end of an aiff, or None if it's silent
+/// all the way through
+pub fn silence(file_path: &path::Path) -> Result<Option<(f64, f64)>> {
+    let audio = Audio::read(file_path)?;
+    let rate = f64::from(audio.sample_rate.max(1));
+    Ok(edit::bounds(&audio, edit::SILENCE)
+        .map(|(start, end)| (start as f64 / rate, (audio.frames() - end) as f64 / rate)))
+}
+
+/// True if there's nothing louder than silence in an aiff
+pub fn is_silent(file_path: &path::Path) -> Result<bool> {
+    Ok(!aiff::any_louder(file_path, edit::SILENCE)?)
+}
+
+fn quantize(sample: f32, bits: u16) -> i32 {
+    let max = 2f64.powi(i32::from(bits) - 1);
+    (f64::from(sample) * max).round().max(-max).min(max - 1.0) as i32
+}
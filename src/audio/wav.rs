@@ -0,0 +1,44 @@
+use super::Audio;
+use std::fs::File;
+use std::io::{BufWriter, Result, Write};
+use std::path;
+
+const PCM: u16 = 1;
+const IEEE_FLOAT: u16 = 3;
+
+pub fn write(file_path: &path::Path, audio: &Audio) -> Result<()> {
+    let width = audio.bits.div_ceil(8);
+    let block_align = audio.channels * width;
+    let data_size = audio.samples.len() as u32 * u32::from(width);
+    let padding = data_size % 2;
+
+    let mut file = BufWriter::new(File::create(file_path)?);
+    file.write_all(b"RIFF")?;
+    file.write_all(&(4 + 24 + 8 + data_size + padding).to_le_bytes())?;
+    file.write_all(b"WAVE")?;
+
+    file.write_all(b"fmt ")?;
+    file.write_all(&16u32.to_le_bytes())?;
+    file.write_all(&(if audio.float { IEEE_FLOAT } else { PCM }).to_le_bytes())?;
+    file.write_all(&audio.channels.to_le_bytes())?;
+    file.write_all(&audio.sample_rate.to_le_bytes())?;
+    file.write_all(&(audio.sample_rate * u32::from(block_align)).to_le_bytes())?;
+    file.write_all(&block_align.to_le_bytes())?;
+    file.write_all(&(width * 8).to_le_bytes())?;
+
+    file.write_all(b"data")?;
+    file.write_all(&data_size.to_le_bytes())?;
+    for sample in &audio.samples {
+        if audio.float {
+            file.write_all(&sample.to_le_bytes())?;
+        } else if width == 1 {
+            // 8-bit wav is the odd one out, and unsigned
+            file.write_all(&[(super::quantize(*sample, 8) + 128) as u8])?;
+        } else {
+            let value = super::quantize(*sample, audio.bits) << (u32::from(width) * 8 - u32::from(audio.bits));
+            file.write_all(&value.to_le_bytes()[..usize::from(width)])?;
+        }
+    }
+    file.write_all(&vec![0; padding as usize])?;
+    file.flush()
+}
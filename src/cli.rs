@@ -0,0 +1,318 @@
+use super::audio::{mix::TrackMix, Format};
+use super::diff;
+use super::disk::Disk;
+use super::operator::Operator;
+use super::serve;
+use super::song::Song;
+use super::sync::Policy;
+use super::watch;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+const USAGE: &str = "usage:
+    save-op1
+    save-op1 menu
+    save-op1 mixdown <slug> [--wav] [track_N=gain,pan ...]
+    save-op1 stems <slug> [label ...]
+    save-op1 draw <slug>
+    save-op1 loudness <slug>
+    save-op1 trim <slug> [fade_in] [fade_out]
+    save-op1 load-files <file> [file ...]
+    save-op1 patches
+    save-op1 snapshot
+    save-op1 gc
+    save-op1 name [name]
+    save-op1 songs [device]
+    save-op1 snapshots [device]
+    save-op1 album <name> <side a name> <side b name>
+    save-op1 release <name> <side a name> <side b name> [cover]
+    save-op1 site [--upload]
+    save-op1 serve [--lan | address]
+    save-op1 diff [snapshot or slug]
+    save-op1 sync [device|archive|newer]
+    save-op1 sync mark|unmark <patch> [patch ...]
+    save-op1 watch [mount ...]
+    save-op1 search <words> [#tag ...]
+    save-op1 tag <patch> <tag> [tag ...]";
+
+fn usage() -> Error {
+    Error::new(ErrorKind::InvalidInput, USAGE)
+}
+
+// track_2=-3,0.5 means track_2 at -3dB, panned halfway right
+fn parse_track_mix(arg: &str) -> Option<(usize, TrackMix)> {
+    let mut parts = arg.splitn(2, '=');
+    let track: usize = parts.next()?.strip_prefix("track_")?.parse().ok()?;
+    let mut values = parts.next()?.splitn(2, ',');
+    let gain = values.next()?.parse().ok()?;
+    let pan = match values.next() {
+        Some(pan) => pan.parse().ok()?,
+        None => 0.0,
+    };
+    if !(1..=4).contains(&track) {
+        return None;
+    }
+    Some((track - 1, TrackMix { gain, pan }))
+}
+
+fn mixdown(disk: &Disk, args: &[String]) -> Result<()> {
+    let slug = args.first().ok_or_else(usage)?;
+    let mut format = Format::Aiff;
+    let mut mix = vec![TrackMix::default(); 4];
+
+    for arg in &args[1..] {
+        if arg == "--wav" {
+            format = Format::Wav;
+        } else {
+            let (track, track_mix) = parse_track_mix(arg).ok_or_else(usage)?;
+            mix[track] = track_mix;
+        }
+    }
+
+    disk.mixdown(slug, &mix, format)?;
+    Ok(())
+}
+
+fn stems(disk: &Disk, args: &[String]) -> Result<()> {
+    let slug = args.first().ok_or_else(usage)?;
+    let labels: Vec<String> = (1..=4)
+        .map(|track| match args.get(track) {
+            Some(label) => label.to_owned(),
+            None => format!("track_{}", track),
+        })
+        .collect();
+
+    let project = disk.export_stems(slug, &labels)?;
+    println!("wrote {}", project.to_str().unwrap());
+    Ok(())
+}
+
+fn loudness(disk: &Disk, args: &[String]) -> Result<()> {
+    let song = disk.song(args.first().ok_or_else(usage)?)?;
+    println!("{}", disk.measure_loudness(&song)?);
+    Ok(())
+}
+
+fn trim(disk: &Disk, args: &[String]) -> Result<()> {
+    let song = disk.song(args.first().ok_or_else(usage)?)?;
+    let seconds = |index: usize| -> Result<f64> {
+        match args.get(index) {
+            Some(arg) => arg.parse().map_err(|_| usage()),
+            None => Ok(0.0),
+        }
+    };
+
+    let (start, end) = disk
+        .silence(&song)?
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "that side is silent"))?;
+    println!("trimming {:.1}s from the start and {:.1}s from the end", start, end);
+    disk.save_master(&song, seconds(1)?, seconds(2)?)
+}
+
+fn load_files(op1_dir: &path::PathBuf, args: &[String]) -> Result<()> {
+    if args.is_empty() {
+        return Err(usage());
+    }
+    let op1 = Operator::new(op1_dir)?;
+    let sources: Vec<path::PathBuf> = args.iter().map(path::PathBuf::from).collect();
+    op1.load_files(&sources)
+}
+
+// every patch on the op-1 as json, for keeping a catalogue
+fn patches(op1_dir: &path::PathBuf) -> Result<()> {
+    let op1 = Operator::new(op1_dir)?;
+    let json = serde_json::to_string_pretty(&op1.patches()?)
+        .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn snapshot(op1_dir: &path::PathBuf, disk: &Disk) -> Result<()> {
+    let op1 = Operator::new(op1_dir)?;
+    println!("saved snapshot {}", disk.snapshot(op1.path(), &op1.device)?);
+    Ok(())
+}
+
+// with no name, says what the plugged in op-1 is called
+fn name(op1_dir: &path::PathBuf, args: &[String]) -> Result<()> {
+    let mut op1 = Operator::new(op1_dir)?;
+    if let Some(name) = args.first() {
+        op1.set_name(name)?;
+    }
+    println!("{}", op1.device);
+    Ok(())
+}
+
+fn songs(disk: &Disk, args: &[String]) -> Result<()> {
+    let mut songs = match args.first() {
+        Some(device) => disk.list_songs_from(device)?,
+        None => disk.list_songs()?,
+    };
+    songs.sort();
+    for slug in songs {
+        let device = disk.metadata(&slug)?.device;
+        println!("{}  {}", slug, device.as_deref().unwrap_or("?"));
+    }
+    Ok(())
+}
+
+fn snapshots(disk: &Disk, args: &[String]) -> Result<()> {
+    let snapshots = match args.first() {
+        Some(device) => disk.list_snapshots_from(device)?,
+        None => disk.list_snapshots()?,
+    };
+    for snapshot in snapshots {
+        let device = disk.manifest(&snapshot)?.device;
+        println!("{}  {}", snapshot, device.as_deref().unwrap_or("?"));
+    }
+    Ok(())
+}
+
+// saves both sides of the plugged in op-1 as one album
+fn album<'disk>(op1_dir: &path::PathBuf, disk: &'disk Disk, args: &[String]) -> Result<Vec<Song<'disk>>> {
+    if args.len() < 3 {
+        return Err(usage());
+    }
+    let op1 = Operator::new(op1_dir)?;
+    let artist = disk.config()?.artist;
+    let mut songs = vec![];
+    for (side, name) in [&op1.album.side_a, &op1.album.side_b].iter().zip(&args[1..3]) {
+        let mut song = Song::new(disk, name, &artist);
+        song.device = Some(op1.device.to_owned());
+        println!("copying {}", side);
+        disk.save_aif(&song, side.path().ok_or_else(usage)?)?;
+        songs.push(song);
+    }
+    println!("saved album {}", disk.save_album(&args[0], &artist, &mut songs)?);
+    Ok(songs)
+}
+
+fn release(op1_dir: &path::PathBuf, disk: &Disk, args: &[String]) -> Result<()> {
+    let mut songs = album(op1_dir, disk, args)?;
+    let cover = args.get(3).map(path::Path::new);
+    let zip = disk.release(&args[0], &disk.config()?.artist, &mut songs, cover)?;
+    println!("released {}", zip.to_str().unwrap());
+    Ok(())
+}
+
+fn site(disk: &Disk, args: &[String]) -> Result<()> {
+    if args.first().map(String::as_ref) == Some("--upload") {
+        return disk.upload_site();
+    }
+    println!("built {}", disk.build_site()?.to_str().unwrap());
+    if disk.config()?.site.url.is_empty() {
+        println!("no site url in config.json, so no podcast feed");
+    }
+    Ok(())
+}
+
+fn collect_garbage(disk: &Disk) -> Result<()> {
+    let (count, bytes) = disk.collect_garbage()?;
+    println!("deleted {} files, {} bytes", count, bytes);
+    Ok(())
+}
+
+fn diff(op1_dir: &path::PathBuf, disk: &Disk, args: &[String]) -> Result<()> {
+    let op1 = Operator::new(op1_dir)?;
+    let name = match args.first() {
+        Some(name) => name.to_owned(),
+        None => disk
+            .list_snapshots_from(&op1.device)?
+            .pop()
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "no snapshots yet"))?,
+    };
+
+    let (backup, scope) = disk.backup_files(&name, &*op1.layout)?;
+    let changes = diff::diff(&op1.files(&scope)?, &backup)?;
+    println!("{} compared to {}", op1.layout.model(), name);
+    if changes.is_empty() {
+        println!("no changes");
+    }
+    for change in changes {
+        println!("{}", change);
+    }
+    Ok(())
+}
+
+// with no policy, conflicts get asked about
+fn sync(op1_dir: &path::Path, disk: &Disk, args: &[String]) -> Result<()> {
+    let log = match args.first().map(String::as_ref) {
+        Some("mark") | Some("unmark") => {
+            let mut state = disk.sync_state()?;
+            for key in &args[1..] {
+                state.mark(key, args[0] == "mark")?;
+            }
+            return disk.write_sync_state(&state);
+        }
+        Some(policy) => {
+            let policy = Policy::parse(policy).ok_or_else(usage)?;
+            disk.sync_patches(op1_dir, |conflict| policy.resolve(conflict))?
+        }
+        None => disk.sync_patches(op1_dir, super::ask_about_conflict)?,
+    };
+    for line in log {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+// mounts given here are watched instead of the ones in config.json
+fn watch(disk: &Disk, args: &[String]) -> Result<()> {
+    let mut config = disk.config()?;
+    if !args.is_empty() {
+        config.watch.mounts = args.iter().map(path::PathBuf::from).collect();
+    }
+    watch::watch(disk, &config)
+}
+
+fn search(disk: &Disk, args: &[String]) -> Result<()> {
+    let index = disk.patch_index()?;
+    for patch in index.search(&args.join(" ")) {
+        println!("{}", patch);
+    }
+    Ok(())
+}
+
+fn tag(disk: &Disk, args: &[String]) -> Result<()> {
+    if args.len() < 2 {
+        return Err(usage());
+    }
+    let mut index = disk.patch_index()?;
+    index.tag(&args[0], &args[1..])?;
+    disk.write_patch_index(&index)
+}
+
+pub fn run(op1_dir: &path::PathBuf, disk: &Disk, args: &[String]) -> Result<()> {
+    match args[0].as_ref() {
+        "mixdown" => mixdown(disk, &args[1..]),
+        "stems" => stems(disk, &args[1..]),
+        "loudness" => loudness(disk, &args[1..]),
+        "trim" => trim(disk, &args[1..]),
+        "load-files" => load_files(op1_dir, &args[1..]),
+        "patches" => patches(op1_dir),
+        "snapshot" => snapshot(op1_dir, disk),
+        "name" => name(op1_dir, &args[1..]),
+        "songs" => songs(disk, &args[1..]),
+        "snapshots" => snapshots(disk, &args[1..]),
+        "album" => album(op1_dir, disk, &args[1..]).map(|_| ()),
+        "release" => release(op1_dir, disk, &args[1..]),
+        "site" => site(disk, &args[1..]),
+        "serve" => serve::serve(
+            op1_dir,
+            disk,
+            match args.get(1).map(String::as_ref) {
+                None => "127.0.0.1:8001",
+                Some("--lan") => "0.0.0.0:8001",
+                Some(address) => address,
+            },
+        ),
+        "gc" => collect_garbage(disk),
+        "diff" => diff(op1_dir, disk, &args[1..]),
+        "sync" => sync(op1_dir, disk, &args[1..]),
+        "watch" => watch(disk, &args[1..]),
+        "search" => search(disk, &args[1..]),
+        "tag" => tag(disk, &args[1..]),
+        "draw" => disk.draw(args.get(1).ok_or_else(usage)?),
+        _ => Err(usage()),
+    }
+}
@@ -0,0 +1,96 @@
+use super::json;
+use serde::{Deserialize, Serialize};
+use std::io::Result;
+use std::path;
+
+/// What to do when the op-1 turns up
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WatchAction {
+    Snapshot,
+    SaveAlbum,
+    Notify,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Watch {
+    /// where to look for the op-1. it can be the mount itself, or one or two
+    /// levels above it, like /media or /media/chee
+    pub mounts: Vec<path::PathBuf>,
+    /// seconds between looks
+    pub interval: u64,
+    pub actions: Vec<WatchAction>,
+}
+
+impl Default for Watch {
+    fn default() -> Watch {
+        Watch {
+            mounts: vec![path::PathBuf::from("/media"), path::PathBuf::from("/run/media")],
+            interval: 2,
+            actions: vec![WatchAction::Snapshot, WatchAction::Notify],
+        }
+    }
+}
+
+/// Shell commands to run after things happen to a song. They get the song as
+/// json on stdin, and as SAVE_OP1_* environment variables
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hooks {
+    pub save: Vec<String>,
+    pub export: Vec<String>,
+    pub upload: Vec<String>,
+    /// a desktop notification for each of them too
+    pub notify: bool,
+}
+
+/// The little website made from the songs
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Site {
+    pub title: String,
+    pub description: String,
+    /// where it ends up, for the links in the feed
+    pub url: String,
+    /// somewhere rsync can put it
+    pub upload: String,
+}
+
+impl Default for Site {
+    fn default() -> Site {
+        Site {
+            title: "quiet party".to_owned(),
+            description: String::new(),
+            url: String::new(),
+            upload: "snoot:site".to_owned(),
+        }
+    }
+}
+
+/// Settings, from config.json in the music dir
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub artist: String,
+    pub watch: Watch,
+    pub hooks: Hooks,
+    pub site: Site,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            artist: "quiet party".to_owned(),
+            watch: Watch::default(),
+            hooks: Hooks::default(),
+            site: Site::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn read(file_path: &path::Path) -> Result<Config> {
+        json::read(file_path)
+    }
+}
@@ -0,0 +1,46 @@
+use super::audio::Audio;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// so jobs decoding at the same time don't write over each other
+static DECODES: AtomicUsize = AtomicUsize::new(0);
+
+/// Decode anything ffmpeg can read, at `sample_rate` and mixed down to `channels`
+pub fn decode(source: &path::Path, sample_rate: u32, channels: u16) -> Result<Audio> {
+    let target = std::env::temp_dir().join(format!(
+        "save-op1-{}-{}.aif",
+        std::process::id(),
+        DECODES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let audio = ffmpeg(source, &target, sample_rate, channels);
+    // ffmpeg can leave half a file behind when it fails
+    let _ = std::fs::remove_file(&target);
+    audio
+}
+
+fn ffmpeg(source: &path::Path, target: &path::Path, sample_rate: u32, channels: u16) -> Result<Audio> {
+    let output = std::process::Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-i",
+            source.to_str().unwrap(),
+            "-ar",
+            &sample_rate.to_string(),
+            "-ac",
+            &channels.to_string(),
+            "-c:a",
+            "pcm_s24be",
+            target.to_str().unwrap(),
+        ])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("ffmpeg couldn't read {}", source.to_str().unwrap()),
+        ));
+    }
+
+    Audio::read(target)
+}
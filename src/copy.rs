@@ -1,17 +1,129 @@
 use pbr::{ProgressBar, Units};
-use std::fs::File;
-use std::io::{copy, Result, Write};
+use std::fs::{read_dir, File};
+use std::io::{copy, Result, Stdout, Write};
 use std::path;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
 use tee_readwrite::TeeWriter;
 
+/// How far along a copy is, in bytes
+pub struct Progress {
+    pub name: String,
+    pub done: u64,
+    pub total: u64,
+}
+
+// when something else owns the screen, copies tell it how they're going
+// rather than drawing progress bars all over it
+static REPORT_TO: Mutex<Option<Sender<Progress>>> = Mutex::new(None);
+
+// and the same goes for anything said along the way
+static SAY_TO: Mutex<Option<Sender<String>>> = Mutex::new(None);
+
+/// Send copy progress somewhere instead of drawing it, or None to draw it again
+pub fn report_progress(sender: Option<Sender<Progress>>) {
+    *REPORT_TO.lock().unwrap() = sender;
+}
+
+/// Send the lines `say` gets somewhere instead of printing them, or None to
+/// print them again
+pub fn report_messages(sender: Option<Sender<String>>) {
+    *SAY_TO.lock().unwrap() = sender;
+}
+
+/// Print a line about how things are going, or pass it on to whatever owns
+/// the screen
+pub fn say(message: &str) {
+    match SAY_TO.lock().unwrap().as_ref() {
+        Some(sender) => {
+            let _ = sender.send(message.to_owned());
+        }
+        None => println!("{}", message),
+    }
+}
+
+/// Keeps track of bytes going past, one way or the other
+pub enum Meter {
+    Bar(Box<ProgressBar<Stdout>>),
+    Report(Sender<Progress>, Progress),
+}
+
+impl Meter {
+    pub fn new(source: &path::Path, bytes: u64) -> Meter {
+        if let Some(sender) = REPORT_TO.lock().unwrap().as_ref() {
+            let name = source.file_name().map_or(String::new(), |name| name.to_str().unwrap().to_owned());
+            return Meter::Report(
+                sender.clone(),
+                Progress {
+                    name,
+                    done: 0,
+                    total: bytes,
+                },
+            );
+        }
+        let mut progress_bar = ProgressBar::new(bytes);
+        progress_bar.set_units(Units::Bytes);
+        Meter::Bar(Box::new(progress_bar))
+    }
+
+    pub fn finish(&mut self) {
+        if let Meter::Bar(progress_bar) = self {
+            progress_bar.finish_print("yay!");
+        }
+    }
+}
+
+impl Write for Meter {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        match self {
+            Meter::Bar(progress_bar) => progress_bar.write(buf),
+            Meter::Report(sender, progress) => {
+                let before = progress.done * 100 / progress.total.max(1);
+                progress.done += buf.len() as u64;
+                // a message a percent is plenty
+                if progress.done * 100 / progress.total.max(1) != before {
+                    let _ = sender.send(Progress {
+                        name: progress.name.to_owned(),
+                        done: progress.done,
+                        total: progress.total,
+                    });
+                }
+                Ok(buf.len())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Copy with a progress bar. The target gets the usual permissions rather than
+/// the source's, so a copy out of a read-only snapshot can be changed.
 pub fn copy_file(source: &path::PathBuf, target: &path::PathBuf) -> Result<()> {
-    let mut source = File::open(source)?;
-    let bytes = source.metadata()?.len() as u64;
-    let mut progress_bar = ProgressBar::new(bytes);
-    progress_bar.set_units(Units::Bytes);
+    let mut source_file = File::open(source)?;
+    let bytes = source_file.metadata()?.len() as u64;
+    let mut meter = Meter::new(source, bytes);
     let mut target = File::create(target)?;
-    let mut tee = TeeWriter::new(&mut target, &mut progress_bar);
-    copy(&mut source, &mut tee)?;
-    progress_bar.finish_print("yay!");
+    {
+        let mut tee = TeeWriter::new(&mut target, &mut meter);
+        copy(&mut source_file, &mut tee)?;
+    }
+    meter.finish();
     Ok(())
 }
+
+/// Every file under a directory, however deep
+pub fn list_files(dir_path: &path::Path) -> Result<Vec<path::PathBuf>> {
+    let mut files = vec![];
+    for entry in read_dir(dir_path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            files.extend(list_files(&entry.path())?);
+        } else {
+            files.push(entry.path());
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,73 @@
+use super::audio::aiff;
+use super::store::hash_file;
+use std::collections::BTreeMap;
+use std::io::Result;
+use std::path;
+
+pub enum Change {
+    Added(String),
+    Removed(String),
+    Changed(String, Vec<String>),
+}
+
+impl std::fmt::Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Change::Added(name) => write!(f, "+ {}", name),
+            Change::Removed(name) => write!(f, "- {}", name),
+            Change::Changed(name, reasons) => write!(f, "~ {} ({})", name, reasons.join(", ")),
+        }
+    }
+}
+
+fn duration(file_path: &path::Path) -> Option<f64> {
+    let info = aiff::info(file_path).ok()?;
+    Some(info.frames as f64 / f64::from(info.sample_rate.max(1)))
+}
+
+// size first, since it's free. the hash only matters when the size matches
+fn compare(device: &path::Path, backup: &path::Path) -> Result<Vec<String>> {
+    let mut reasons = vec![];
+    let device_size = device.metadata()?.len();
+    let backup_size = backup.metadata()?.len();
+    if device_size != backup_size {
+        reasons.push(format!("size {} -> {}", backup_size, device_size));
+    } else if hash_file(device)? != hash_file(backup)? {
+        reasons.push("contents".to_owned());
+    }
+
+    if !reasons.is_empty() {
+        if let (Some(before), Some(after)) = (duration(backup), duration(device)) {
+            if (before - after).abs() > 0.001 {
+                reasons.push(format!("duration {:.1}s -> {:.1}s", before, after));
+            }
+        }
+    }
+    Ok(reasons)
+}
+
+/// What's different on the op-1 compared to a backup. Both sides are keyed by
+/// path on the op-1, like tape/track_1.aif
+pub fn diff(
+    device: &BTreeMap<String, path::PathBuf>,
+    backup: &BTreeMap<String, path::PathBuf>,
+) -> Result<Vec<Change>> {
+    let mut changes = vec![];
+    for (name, device_path) in device {
+        match backup.get(name) {
+            None => changes.push(Change::Added(name.to_owned())),
+            Some(backup_path) => {
+                let reasons = compare(device_path, backup_path)?;
+                if !reasons.is_empty() {
+                    changes.push(Change::Changed(name.to_owned(), reasons));
+                }
+            }
+        }
+    }
+    for name in backup.keys() {
+        if !device.contains_key(name) {
+            changes.push(Change::Removed(name.to_owned()));
+        }
+    }
+    Ok(changes)
+}
@@ -1,7 +1,21 @@
-use super::copy::copy_file;
-use super::operator::Track;
+use super::audio::{self, loudness::Loudness, mix::TrackMix, Audio, Format};
+use super::config::Config;
+use super::copy::{copy_file, list_files, say};
+use super::hooks::{self, Event, Payload};
+use super::library::PatchIndex;
+use super::metadata::{AlbumMetadata, Metadata, Trim};
+use super::operator::{Layout, Track, FOLDERS};
+use super::reaper;
+use super::release::{self, ReleaseTrack};
+use super::site::{self, SiteSong};
 use super::song::Song;
-use std::fs::{create_dir_all, read_dir};
+use super::store::{Entry, Manifest, Store};
+use super::sync::{self, Conflict, SyncState, Winner};
+use super::time;
+use super::waveform;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs::{create_dir_all, read_dir, OpenOptions};
+use std::io::Write;
 use std::io::{Error, ErrorKind, Result};
 use std::path;
 
@@ -78,17 +92,69 @@ impl SongsPath {
         format!("{}/{}/{}.mp3", self.path, song.slug(), song.slug())
     }
 
+    fn mixdown(&self, song: &SongArg, format: Format) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}/{}/{}-mixdown.{}",
+            self.path,
+            song.slug(),
+            song.slug(),
+            format.extension()
+        ))
+    }
+
+    fn stems(&self, song: &SongArg) -> path::PathBuf {
+        path::PathBuf::from(format!("{}/{}/stems", self.path, song.slug()))
+    }
+
+    fn metadata(&self, song: &SongArg) -> path::PathBuf {
+        path::PathBuf::from(format!("{}/{}/{}.json", self.path, song.slug(), song.slug()))
+    }
+
+    fn picture(&self, song: &SongArg, name: &str) -> path::PathBuf {
+        path::PathBuf::from(format!("{}/{}/{}.png", self.path, song.slug(), name))
+    }
+
     fn aif(&self, song: &SongArg) -> path::PathBuf {
         path::PathBuf::from(&self.aif_string(song))
     }
 
-    fn _mp3(&self, song: &SongArg) -> path::PathBuf {
+    fn master_string(&self, song: &SongArg) -> String {
+        format!("{}/{}/{}.master.aif", self.path, song.slug(), song.slug())
+    }
+
+    /// The trimmed master if there is one, otherwise the side as it was saved
+    fn export_source_string(&self, song: &SongArg) -> String {
+        let master = self.master_string(song);
+        if path::Path::new(&master).exists() {
+            master
+        } else {
+            self.aif_string(song)
+        }
+    }
+
+    fn mp3(&self, song: &SongArg) -> path::PathBuf {
         path::PathBuf::from(&self.mp3_string(song))
     }
 }
 
+// an error if the command doesn't work, with the last thing it complained about
+fn run(command: &mut std::process::Command, failed: &str) -> Result<()> {
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(Error::other(format!(
+            "{} ({})",
+            failed,
+            stderr.lines().last().unwrap_or("no reason given")
+        )));
+    }
+    Ok(())
+}
+
 pub struct Disk {
+    path: path::PathBuf,
     songs: SongsPath,
+    store: Store,
     /*
     synth_dir: path::PathBuf,
     drum_dir: path::PathBuf,
@@ -104,7 +170,7 @@ impl Disk {
         create_dir_all(self.songs.tape(&SongArg::Song(song)))
     }
 
-    fn list_songs(&self) -> Result<Vec<String>> {
+    pub fn list_songs(&self) -> Result<Vec<String>> {
         let mut names = vec![];
 
         for dir in read_dir(&self.songs.path)? {
@@ -126,6 +192,17 @@ impl Disk {
         Ok(names)
     }
 
+    /// Songs that came from one particular op-1
+    pub fn list_songs_from(&self, device: &str) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for slug in self.list_songs()? {
+            if self.metadata(&slug)?.device.as_deref() == Some(device) {
+                names.push(slug);
+            }
+        }
+        Ok(names)
+    }
+
     pub fn list_tapes(&self) -> Result<Vec<String>> {
         let mut names = vec![];
 
@@ -139,9 +216,50 @@ impl Disk {
         Ok(names)
     }
 
+    // hooks and notifications from config.json
+    fn after(&self, event: Event, song: &Song, file: &str) -> Result<()> {
+        let metadata = self.metadata(&song.slug)?;
+        let payload = Payload {
+            event,
+            slug: &song.slug,
+            name: &song.name,
+            artist: &song.artist,
+            track: song.track,
+            file,
+            metadata: &metadata,
+        };
+        hooks::run(&self.config()?.hooks, &payload)
+    }
+
+    // which op-1, and which side of it, a song came from
+    fn note_origin(&self, song: &Song, source: Option<&path::PathBuf>) -> Result<()> {
+        let mut metadata = self.metadata(&song.slug)?;
+        if let Some(source) = source {
+            metadata.side = source.file_name().map(|name| name.to_str().unwrap().to_owned());
+        }
+        if song.device.is_some() {
+            metadata.device = song.device.to_owned();
+        }
+        self.write_metadata(&song.slug, &metadata)
+    }
+
     pub fn save_aif(&self, song: &Song, source: &path::PathBuf) -> Result<()> {
         self.make_song_dir(song)?;
         copy_file(source, &self.songs.aif(&SongArg::Song(song)))?;
+
+        self.note_origin(song, Some(source))?;
+        self.after(Event::Save, song, &self.songs.aif_string(&SongArg::Song(song)))?;
+        self.refresh_site();
+        Ok(())
+    }
+
+    /// Save a piece of audio as the song's side, rather than copying one over
+    pub fn write_aif(&self, song: &Song, side: &Audio) -> Result<()> {
+        self.make_song_dir(song)?;
+        side.write(&self.songs.aif(&SongArg::Song(song)), Format::Aiff)?;
+        self.note_origin(song, None)?;
+        self.after(Event::Save, song, &self.songs.aif_string(&SongArg::Song(song)))?;
+        self.refresh_site();
         Ok(())
     }
 
@@ -149,63 +267,832 @@ impl Disk {
         self.make_tape_dir(song)?;
 
         for track in tracks {
-            println!("copying {}", track);
+            say(&format!("copying {}", track));
             copy_file(
                 &path::PathBuf::from(track.path()),
                 &self.songs.tape_track(&SongArg::Song(song), track),
             )?;
         }
 
+        self.note_origin(song, None)?;
+        self.after(Event::Save, song, &self.songs.tape_string(&SongArg::Song(song)))?;
+        self.refresh_site();
         Ok(())
     }
 
-    pub fn create_mp3(&self, song: &Song) -> Result<()> {
-        std::process::Command::new("ffmpeg")
-            .args(&[
-                "-i",
-                &self.songs.aif_string(&SongArg::Song(song)),
-                "-ab",
-                "320k",
-                &self.songs.mp3_string(&SongArg::Song(song)),
-            ])
-            .output()?;
+    /// Saving something off the op-1, the same from the menu, the tui and the
+    /// web page. The side is copied in, trimmed to `fades` if there are any,
+    /// measured, made into an mp3 and drawn, and `tracks` come along as the
+    /// song's tape. With no side it's just the tape.
+    pub fn save_song(
+        &self,
+        song: &Song,
+        side: Option<&path::PathBuf>,
+        tracks: Vec<&Track>,
+        fades: Option<(f64, f64)>,
+        normalize: Option<f64>,
+    ) -> Result<()> {
+        if let Some(side) = side {
+            say(&format!("copying {}", side.file_name().unwrap().to_str().unwrap()));
+            self.save_aif(song, side)?;
+            if let Some((fade_in, fade_out)) = fades {
+                say("trimming");
+                self.save_master(song, fade_in, fade_out)?;
+            }
+        }
+        if !tracks.is_empty() {
+            self.save_tape(song, tracks)?;
+        }
+        if side.is_some() {
+            say("measuring loudness");
+            say(&self.measure_loudness(song)?.to_string());
+            say("making the mp3");
+            self.create_mp3(song, normalize)?;
+        }
+        self.draw(&song.slug)
+    }
+
+    pub fn mixdown(&self, slug: &str, mix: &[TrackMix], format: Format) -> Result<path::PathBuf> {
+        let mut tracks = vec![];
+        for (number, track_mix) in (1..=4).zip(mix) {
+            let track_path = self.track_path(slug, number);
+            // tracks that were empty when the tape was saved aren't there at all
+            if track_path.exists() {
+                tracks.push((Audio::read(&track_path)?, *track_mix));
+            }
+        }
 
+        let target = self.songs.mixdown(&SongArg::Slug(slug), format);
+        say(&format!("mixing down to {}", target.to_str().unwrap()));
+        audio::mix::mixdown(&tracks).write(&target, format)?;
+        Ok(target)
+    }
+
+    pub fn export_stems(&self, slug: &str, labels: &[String]) -> Result<path::PathBuf> {
+        let song = SongArg::Slug(slug);
+        let mut tracks = vec![];
+        for (number, label) in (1..=4).zip(labels) {
+            let track_path = self.track_path(slug, number);
+            if track_path.exists() {
+                tracks.push((number, Audio::read(&track_path)?, label));
+            }
+        }
+
+        let stems_dir = self.songs.stems(&song);
+        create_dir_all(&stems_dir)?;
+
+        // everything starts at zero and runs the same length, so the stems line up in a daw
+        let frames = tracks.iter().map(|(_, track, _)| track.frames()).max().unwrap_or(0);
+        let sample_rate = tracks.first().map_or(44100, |(_, track, _)| track.sample_rate);
+        let mut stems = vec![];
+        for (number, mut track, label) in tracks {
+            track.pad_to(frames);
+            let file_name = format!("{}-{}.wav", number, slug::slugify(label));
+            say(&format!("writing {}", file_name));
+            track.write(&stems_dir.join(&file_name), Format::Wav)?;
+            stems.push(reaper::Stem {
+                name: label.to_owned(),
+                file_name,
+                length: track.duration(),
+            });
+        }
+
+        let project_path = stems_dir.join(format!("{}.rpp", slug));
+        std::fs::write(&project_path, reaper::project(sample_rate, &stems))?;
+        Ok(project_path)
+    }
+
+    /// Waveform and spectrogram pictures for the side, if there is one, and
+    /// a waveform for each tape track
+    pub fn draw(&self, slug: &str) -> Result<()> {
+        let song = SongArg::Slug(slug);
+        if self.songs.aif(&song).exists() {
+            say(&format!("drawing {}", slug));
+            let side = Audio::read(path::Path::new(&self.songs.export_source_string(&song)))?;
+            waveform::draw(&side, &self.songs.picture(&song, slug))?;
+            waveform::draw_spectrogram(
+                &side,
+                &self.songs.picture(&song, &format!("{}-spectrogram", slug)),
+            )?;
+        }
+
+        if self.songs.tape_exists(&song) {
+            for track_path in self.track_paths(slug) {
+                let track_path = path::PathBuf::from(track_path);
+                // tracks left out when saving aren't there at all
+                if !track_path.exists() {
+                    continue;
+                }
+                let track = Audio::read(&track_path).map_err(|error| {
+                    Error::new(error.kind(), format!("couldn't draw {}: {}", track_path.to_str().unwrap(), error))
+                })?;
+                waveform::draw(&track, &track_path.with_extension("png"))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn ascii_waveform(&self, slug: &str, width: usize, height: usize) -> Result<String> {
+        let side = Audio::read(&self.songs.aif(&SongArg::Slug(slug)))?;
+        Ok(waveform::ascii(&side, width, height))
+    }
+
+    /// Seconds of silence at the start and end of the saved side, or None
+    /// if it's silent all the way through
+    pub fn silence(&self, song: &Song) -> Result<Option<(f64, f64)>> {
+        audio::silence(&self.songs.aif(&SongArg::Song(song)))
+    }
+
+    /// Cut the silence off either end of the side into a master, leaving the
+    /// original alone. The master is what gets exported from then on.
+    pub fn save_master(&self, song: &Song, fade_in: f64, fade_out: f64) -> Result<()> {
+        let side = Audio::read(&self.songs.aif(&SongArg::Song(song)))?;
+        let (start, end) = audio::edit::bounds(&side, audio::edit::SILENCE)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "that side is silent"))?;
+        let mut master = audio::edit::slice(&side, start, end);
+        audio::edit::fade(&mut master, fade_in, fade_out);
+        master.write(
+            path::Path::new(&self.songs.master_string(&SongArg::Song(song))),
+            Format::Aiff,
+        )?;
+
+        let rate = f64::from(side.sample_rate);
+        let mut metadata = self.metadata(&song.slug)?;
+        metadata.trim = Some(Trim {
+            start: start as f64 / rate,
+            end: end as f64 / rate,
+            fade_in,
+            fade_out,
+        });
+        // the old measurement was of the untrimmed side
+        metadata.loudness = None;
+        self.write_metadata(&song.slug, &metadata)
+    }
+
+    /// A song that's already on disk, named from its metadata where we can
+    pub fn song(&self, slug: &str) -> Result<Song<'_>> {
+        let metadata = self.metadata(slug)?;
+        let mut song = Song::new(
+            self,
+            metadata.name.as_deref().unwrap_or(slug),
+            metadata.artist.as_deref().unwrap_or("quiet party"),
+        );
+        song.slug = slug.to_owned();
+        song.track = metadata.track;
+        song.device = metadata.device;
+        Ok(song)
+    }
+
+    pub fn metadata(&self, slug: &str) -> Result<Metadata> {
+        Metadata::read(&self.songs.metadata(&SongArg::Slug(slug)))
+    }
+
+    pub fn write_metadata(&self, slug: &str, metadata: &Metadata) -> Result<()> {
+        Metadata::write(metadata, &self.songs.metadata(&SongArg::Slug(slug)))
+    }
+
+    pub fn measure_loudness(&self, song: &Song) -> Result<Loudness> {
+        let side = Audio::read(path::Path::new(
+            &self.songs.export_source_string(&SongArg::Song(song)),
+        ))?;
+        let loudness = audio::loudness::measure(&side);
+
+        let mut metadata = self.metadata(&song.slug)?;
+        metadata.name = Some(song.name.to_owned());
+        metadata.artist = Some(song.artist.to_owned());
+        metadata.track = song.track;
+        metadata.loudness = Some(loudness);
+        self.write_metadata(&song.slug, &metadata)?;
+
+        Ok(loudness)
+    }
+
+    /// Make the mp3 and tag it, so the export hooks get the finished thing.
+    /// `normalize` is a target in LUFS, or None to leave the level alone
+    pub fn create_mp3(&self, song: &Song, normalize: Option<f64>) -> Result<()> {
+        let mut args = vec![
+            "-y".to_owned(),
+            "-i".to_owned(),
+            self.songs.export_source_string(&SongArg::Song(song)),
+        ];
+
+        if let Some(target) = normalize {
+            let loudness = match self.metadata(&song.slug)?.loudness {
+                Some(loudness) => loudness,
+                None => self.measure_loudness(song)?,
+            };
+            let gain = loudness.gain_to(target);
+            say(&format!("adjusting level by {:.1}dB", gain));
+            args.push("-af".to_owned());
+            args.push(format!("volume={:.2}dB", gain));
+        }
+
+        args.push("-ab".to_owned());
+        args.push("320k".to_owned());
+        args.push(self.songs.mp3_string(&SongArg::Song(song)));
+
+        run(
+            std::process::Command::new("ffmpeg").args(&args),
+            "ffmpeg couldn't make the mp3",
+        )?;
+        self.tag_mp3(song)?;
+
+        self.after(Event::Export, song, &self.songs.mp3_string(&SongArg::Song(song)))?;
+        self.refresh_site();
         Ok(())
     }
 
-    pub fn tag_mp3(&self, song: &Song) -> Result<()> {
-        let mp3 = taglib::File::new(&self.songs.mp3_string(&SongArg::Song(song))).unwrap();
-        let mut tag = mp3.tag().unwrap();
+    fn mp3_is_current(&self, song: &Song) -> Result<bool> {
+        let mp3 = self.songs.mp3(&SongArg::Song(song));
+        if !mp3.exists() {
+            return Ok(false);
+        }
+        let source = self.songs.export_source_string(&SongArg::Song(song));
+        Ok(mp3.metadata()?.modified()? >= path::Path::new(&source).metadata()?.modified()?)
+    }
+
+    fn tag_mp3(&self, song: &Song) -> Result<()> {
+        let mp3 = taglib::File::new(&self.songs.mp3_string(&SongArg::Song(song)))
+            .map_err(|error| Error::new(ErrorKind::NotFound, format!("couldn't open the mp3 to tag it: {:?}", error)))?;
+        let mut tag = mp3
+            .tag()
+            .map_err(|error| Error::new(ErrorKind::InvalidData, format!("the mp3 has no tags: {:?}", error)))?;
         tag.set_title(&song.name);
         tag.set_artist(&song.artist);
+        if let Some(album) = self.metadata(&song.slug)?.album {
+            let album = self.album(&album)?;
+            if !album.name.is_empty() {
+                tag.set_album(&album.name);
+            }
+        }
+        if let Some(track) = song.track {
+            tag.set_track(track);
+        }
         tag.set_comment("large rabbit");
-        mp3.save();
+        if !mp3.save() {
+            return Err(Error::other("couldn't save the mp3's tags"));
+        }
         Ok(())
     }
 
     pub fn upload_mp3(&self, song: &Song) -> Result<()> {
-        std::process::Command::new("rsync")
-            .args(&[
+        run(
+            std::process::Command::new("rsync").args([
                 "-av",
                 &self.songs.mp3_string(&SongArg::Song(song)),
                 "snoot:music",
-            ])
-            .output()?;
+            ]),
+            "rsync couldn't upload the mp3",
+        )?;
+        self.after(Event::Upload, song, &self.songs.mp3_string(&SongArg::Song(song)))?;
+        self.upload_site()
+    }
+
+    fn site_path(&self) -> path::PathBuf {
+        self.path.join("site")
+    }
+
+    // hardlinked where it can be, since mp3s are big and the site is rebuilt a lot
+    fn publish_file(source: &path::Path, target: &path::Path) -> Result<()> {
+        if target.exists() {
+            if target.metadata()?.len() == source.metadata()?.len()
+                && target.metadata()?.modified()? >= source.metadata()?.modified()?
+            {
+                return Ok(());
+            }
+            std::fs::remove_file(target)?;
+        }
+        if std::fs::hard_link(source, target).is_err() {
+            std::fs::copy(source, target)?;
+        }
         Ok(())
     }
 
+    /// A static site of every song with an mp3, and a podcast feed of them
+    pub fn build_site(&self) -> Result<path::PathBuf> {
+        let config = self.config()?;
+        let site_path = self.site_path();
+        create_dir_all(site_path.join("songs"))?;
+
+        let mut songs = vec![];
+        for slug in self.list_songs()? {
+            let song = SongArg::Slug(&slug);
+            let mp3 = self.songs.mp3(&song);
+            if !mp3.exists() {
+                continue;
+            }
+            let metadata = self.metadata(&slug)?;
+            let mp3_name = format!("songs/{}.mp3", slug);
+            Disk::publish_file(&mp3, &site_path.join(&mp3_name))?;
+            let picture = self.songs.picture(&song, &slug);
+            let picture_name = if picture.exists() {
+                let picture_name = format!("songs/{}.png", slug);
+                Disk::publish_file(&picture, &site_path.join(&picture_name))?;
+                Some(picture_name)
+            } else {
+                None
+            };
+            let album = match &metadata.album {
+                Some(album) => self.album(album).ok().map(|album| album.name).filter(|name| !name.is_empty()),
+                None => None,
+            };
+            let length = audio::aiff::info(path::Path::new(&self.songs.export_source_string(&song)))
+                .map_or(0.0, |info| info.frames as f64 / f64::from(info.sample_rate.max(1)));
+            let published = mp3
+                .metadata()?
+                .modified()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+
+            songs.push(SiteSong {
+                name: metadata.name.unwrap_or_else(|| slug.to_owned()),
+                artist: metadata.artist.unwrap_or_else(|| config.artist.to_owned()),
+                slug,
+                album,
+                mp3: mp3_name,
+                picture: picture_name,
+                size: mp3.metadata()?.len(),
+                published,
+                length,
+            });
+        }
+        songs.sort_by_key(|song| std::cmp::Reverse(song.published));
+
+        std::fs::write(site_path.join("index.html"), site::index(&config.site, &songs))?;
+        let feed_path = site_path.join("feed.xml");
+        match site::feed(&config.site, &songs) {
+            Some(feed) => std::fs::write(feed_path, feed)?,
+            None if feed_path.exists() => std::fs::remove_file(feed_path)?,
+            None => {}
+        }
+        Ok(site_path)
+    }
+
+    // the site is a nice extra, so it's only kept up to date once there is
+    // one, and one song it can't cope with shouldn't stop the saving and
+    // exporting
+    fn refresh_site(&self) {
+        if !self.site_path().join("index.html").exists() {
+            return;
+        }
+        if let Err(error) = self.build_site() {
+            say(&format!("couldn't rebuild the site: {}", error));
+        }
+    }
+
+    pub fn upload_site(&self) -> Result<()> {
+        let site = format!("{}/", self.build_site()?.to_str().unwrap());
+        run(
+            std::process::Command::new("rsync").args(["-av", "--delete", &site, &self.config()?.site.upload]),
+            "rsync couldn't upload the site",
+        )
+    }
+
+    fn albums_path(&self) -> path::PathBuf {
+        self.path.join("albums")
+    }
+
+    pub fn album(&self, slug: &str) -> Result<AlbumMetadata> {
+        AlbumMetadata::read(&self.albums_path().join(format!("{}.json", slug)))
+    }
+
+    /// Link songs together as an album, numbered in the order they're given
+    pub fn save_album(&self, name: &str, artist: &str, songs: &mut [Song]) -> Result<String> {
+        let slug = slug::slugify(name);
+        create_dir_all(self.albums_path())?;
+        let album = AlbumMetadata {
+            name: name.to_owned(),
+            artist: artist.to_owned(),
+            songs: songs.iter().map(|song| song.slug.to_owned()).collect(),
+        };
+        album.write(&self.albums_path().join(format!("{}.json", slug)))?;
+
+        for (index, song) in songs.iter_mut().enumerate() {
+            song.track = Some(index as u32 + 1);
+            let mut metadata = self.metadata(&song.slug)?;
+            metadata.name = Some(song.name.to_owned());
+            metadata.artist = Some(song.artist.to_owned());
+            metadata.track = song.track;
+            metadata.album = Some(slug.to_owned());
+            self.write_metadata(&song.slug, &metadata)?;
+        }
+        Ok(slug)
+    }
+
+    fn releases_path(&self) -> path::PathBuf {
+        self.path.join("releases")
+    }
+
+    /// A folder ready to upload, with the songs numbered in order as mp3,
+    /// flac and wav, the whole thing as one wav with a cue sheet, a cover, a
+    /// tracklist, and all of that again as a zip. `cover` is a picture to use,
+    /// otherwise it's a waveform of the album.
+    pub fn release(
+        &self,
+        title: &str,
+        artist: &str,
+        songs: &mut [Song],
+        cover: Option<&path::Path>,
+    ) -> Result<path::PathBuf> {
+        let slug = slug::slugify(title);
+        let release_dir = self.releases_path().join(&slug);
+        create_dir_all(&release_dir)?;
+
+        let mut sides = vec![];
+        let mut tracks = vec![];
+        let mut start = 0.0;
+        for (index, song) in songs.iter_mut().enumerate() {
+            song.track = Some(index as u32 + 1);
+            let source = self.songs.export_source_string(&SongArg::Song(song));
+            let side = Audio::read(path::Path::new(&source))?;
+            let name = format!("{:02}-{}", index + 1, song.slug);
+            say(&format!("writing {}", name));
+
+            // an mp3 from before has had its level seen to already, as long
+            // as the side or master hasn't changed since
+            if self.mp3_is_current(song)? {
+                self.tag_mp3(song)?;
+            } else {
+                self.create_mp3(song, None)?;
+            }
+            copy_file(
+                &self.songs.mp3(&SongArg::Song(song)),
+                &release_dir.join(format!("{}.mp3", name)),
+            )?;
+            side.write(&release_dir.join(format!("{}.wav", name)), Format::Wav)?;
+            run(
+                std::process::Command::new("ffmpeg")
+                    .args(["-y", "-i", &source])
+                    .arg(release_dir.join(format!("{}.flac", name))),
+                "ffmpeg couldn't make the flac",
+            )?;
+
+            tracks.push(ReleaseTrack {
+                name: song.name.to_owned(),
+                start,
+                length: side.duration(),
+            });
+            start += side.duration();
+            sides.push(side);
+        }
+
+        let album = audio::edit::join(&sides)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "those sides don't sound alike enough to join"))?;
+        let album_name = format!("{}.wav", slug);
+        album.write(&release_dir.join(&album_name), Format::Wav)?;
+        std::fs::write(
+            release_dir.join(format!("{}.cue", slug)),
+            release::cue_sheet(title, artist, &album_name, &tracks),
+        )?;
+        std::fs::write(
+            release_dir.join("tracklist.txt"),
+            release::tracklist(title, artist, &tracks),
+        )?;
+        match cover {
+            Some(cover) => {
+                let extension = cover.extension().and_then(|extension| extension.to_str()).unwrap_or("png");
+                std::fs::copy(cover, release_dir.join(format!("cover.{}", extension)))?;
+            }
+            None => waveform::draw(&album, &release_dir.join("cover.png"))?,
+        }
+
+        let zip_path = self.releases_path().join(format!("{}.zip", slug));
+        if zip_path.exists() {
+            std::fs::remove_file(&zip_path)?;
+        }
+        say("zipping");
+        run(
+            std::process::Command::new("zip")
+                .current_dir(self.releases_path())
+                .args(["-r", "-q"])
+                .arg(&zip_path)
+                .arg(&slug),
+            "zip couldn't make the zip",
+        )?;
+        if !zip_path.exists() {
+            return Err(Error::new(ErrorKind::NotFound, "zip said it worked but there's no zip"));
+        }
+        Ok(zip_path)
+    }
+
     pub fn _tape_path(&self, slug: &str) -> String {
         self.songs.tape_string(&SongArg::Slug(slug))
     }
 
+    /// Every bit of audio saved for a song, the side first and then its tracks
+    pub fn sources(&self, slug: &str) -> Vec<(String, path::PathBuf)> {
+        let mut sources = vec![];
+        let aif = self.songs.aif(&SongArg::Slug(slug));
+        if aif.exists() {
+            sources.push(("side".to_owned(), aif));
+        }
+        for number in 1..=4 {
+            let track_path = self.track_path(slug, number);
+            if track_path.exists() {
+                sources.push((format!("track_{}", number), track_path));
+            }
+        }
+        sources
+    }
+
+    /// Everything saved for a song, relative to its folder
+    pub fn song_files(&self, slug: &str) -> Result<Vec<String>> {
+        let song_path = self.songs.song(&SongArg::Slug(slug));
+        Ok(list_files(&song_path)?
+            .iter()
+            .map(|file_path| file_path.strip_prefix(&song_path).unwrap().to_str().unwrap().to_owned())
+            .collect())
+    }
+
+    /// A file saved for a song, as long as it really is in the song's folder
+    pub fn song_file(&self, slug: &str, relative: &str) -> Option<path::PathBuf> {
+        let inside = |text: &str| {
+            path::Path::new(text)
+                .components()
+                .all(|component| matches!(component, path::Component::Normal(_)))
+        };
+        if !inside(slug) || !inside(relative) {
+            return None;
+        }
+        let file_path = self.songs.song(&SongArg::Slug(slug)).join(relative);
+        if file_path.is_file() {
+            Some(file_path)
+        } else {
+            None
+        }
+    }
+
     pub fn track_paths(&self, slug: &str) -> Vec<String> {
-        let song = &SongArg::Slug(slug);
-        vec![
-            format!("{}/track_1.aif", self.songs.tape_string(song)),
-            format!("{}/track_2.aif", self.songs.tape_string(song)),
-            format!("{}/track_3.aif", self.songs.tape_string(song)),
-            format!("{}/track_4.aif", self.songs.tape_string(song)),
-        ]
+        (1..=4)
+            .map(|number| self.track_path(slug, number).to_str().unwrap().to_owned())
+            .collect()
+    }
+
+    pub fn track_path(&self, slug: &str, number: usize) -> path::PathBuf {
+        path::PathBuf::from(format!(
+            "{}/track_{}.aif",
+            self.songs.tape_string(&SongArg::Slug(slug)),
+            number
+        ))
+    }
+
+    fn snapshots_path(&self) -> path::PathBuf {
+        self.path.join("snapshots")
+    }
+
+    pub fn list_snapshots(&self) -> Result<Vec<String>> {
+        if !self.snapshots_path().exists() {
+            return Ok(vec![]);
+        }
+        let mut names = vec![];
+        for entry in read_dir(self.snapshots_path())? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                names.push(entry.file_name().to_str().unwrap().to_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// Snapshots of one particular op-1, oldest first
+    pub fn list_snapshots_from(&self, device: &str) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for snapshot in self.list_snapshots()? {
+            if self.manifest(&snapshot)?.device.as_deref() == Some(device) {
+                names.push(snapshot);
+            }
+        }
+        Ok(names)
+    }
+
+    /// Every op-1 that has songs or snapshots here
+    pub fn list_devices(&self) -> Result<Vec<String>> {
+        let mut devices = BTreeSet::new();
+        for slug in self.list_songs()? {
+            devices.extend(self.metadata(&slug)?.device);
+        }
+        for snapshot in self.list_snapshots()? {
+            devices.extend(self.manifest(&snapshot)?.device);
+        }
+        Ok(devices.into_iter().collect())
+    }
+
+    pub fn snapshot_path(&self, name: &str) -> path::PathBuf {
+        self.snapshots_path().join(name)
+    }
+
+    pub fn manifest(&self, snapshot: &str) -> Result<Manifest> {
+        Manifest::read(&self.snapshot_path(snapshot).join("manifest.json"))
+    }
+
+    /// Copy everything off the op-1 into a new snapshot named for the time.
+    /// Files are stored once and linked into each snapshot that has them, and
+    /// anything the same size and age as last time isn't even read. Linked
+    /// files are read-only, so copy one out before changing it.
+    pub fn snapshot(&self, op1_path: &path::Path, device: &str) -> Result<String> {
+        let stamp = time::stamp(time::now());
+        let mut name = stamp.to_owned();
+        // two snapshots in the same second mustn't end up in one folder
+        let mut count = 1;
+        while self.snapshot_path(&name).exists() {
+            count += 1;
+            name = format!("{}-{}", stamp, count);
+        }
+        let target = self.snapshot_path(&name);
+        let previous = match self.list_snapshots_from(device)?.last() {
+            Some(last) => self.manifest(last)?,
+            None => Manifest::default(),
+        };
+
+        let mut manifest = Manifest {
+            device: Some(device.to_owned()),
+            ..Manifest::default()
+        };
+        for folder in &FOLDERS {
+            let source = op1_path.join(folder);
+            if !source.exists() {
+                continue;
+            }
+            for file_path in list_files(&source)? {
+                let relative = file_path.strip_prefix(op1_path).unwrap().to_str().unwrap().to_owned();
+                let metadata = file_path.metadata()?;
+                let modified = metadata
+                    .modified()?
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0, |duration| duration.as_secs());
+
+                let hash = match previous.files.get(&relative) {
+                    Some(entry)
+                        if entry.size == metadata.len()
+                            && entry.modified == modified
+                            && self.store.has(&entry.hash) =>
+                    {
+                        entry.hash.to_owned()
+                    }
+                    _ => {
+                        say(&format!("copying {}", relative));
+                        self.store.add(&file_path)?
+                    }
+                };
+                self.store.link(&hash, &target.join(&relative))?;
+                manifest.files.insert(
+                    relative,
+                    Entry {
+                        hash,
+                        size: metadata.len(),
+                        modified,
+                    },
+                );
+            }
+        }
+        create_dir_all(&target)?;
+        manifest.write(&target.join("manifest.json"))?;
+
+        self.reindex_patches()?;
+        Ok(name)
+    }
+
+    /// The files in a snapshot or a saved song, keyed by where they'd be on
+    /// an op-1 laid out like `layout`, and which parts of the op-1 that covers
+    pub fn backup_files(
+        &self,
+        name: &str,
+        layout: &dyn Layout,
+    ) -> Result<(BTreeMap<String, path::PathBuf>, Vec<String>)> {
+        let mut files = BTreeMap::new();
+
+        if self.list_snapshots()?.iter().any(|snapshot| snapshot == name) {
+            let snapshot_path = self.snapshot_path(name);
+            for folder in &FOLDERS {
+                let folder_path = snapshot_path.join(folder);
+                if folder_path.exists() {
+                    for file_path in list_files(&folder_path)? {
+                        let relative = file_path.strip_prefix(&snapshot_path).unwrap();
+                        files.insert(relative.to_str().unwrap().to_owned(), file_path.to_owned());
+                    }
+                }
+            }
+            return Ok((files, FOLDERS.iter().map(|folder| folder.to_string()).collect()));
+        }
+
+        if self.list_songs()?.iter().any(|song| song == name) {
+            let mut scope = vec![];
+            // songs split out of a side don't say which, and wouldn't match it anyway
+            let side = self.metadata(name)?.side;
+            let side = layout
+                .sides()
+                .iter()
+                .find(|key| path::Path::new(key).file_name().and_then(|name| name.to_str()) == side.as_deref())
+                .map(|key| key.to_string());
+            if let Some(side) = side {
+                files.insert(side.to_owned(), self.songs.aif(&SongArg::Slug(name)));
+                scope.push(side);
+            }
+            if self.songs.tape_exists(&SongArg::Slug(name)) {
+                for (index, key) in layout.tracks().iter().enumerate() {
+                    let track_path = self.track_path(name, index + 1);
+                    // tracks left out when saving aren't part of the backup
+                    if track_path.exists() {
+                        files.insert(key.to_string(), track_path);
+                        scope.push(key.to_string());
+                    }
+                }
+            }
+            return Ok((files, scope));
+        }
+
+        Err(Error::new(ErrorKind::NotFound, "no snapshot or song by that name"))
+    }
+
+    /// Throw away stored files no snapshot refers to any more
+    pub fn collect_garbage(&self) -> Result<(usize, u64)> {
+        let mut keep = HashSet::new();
+        for snapshot in self.list_snapshots()? {
+            for entry in self.manifest(&snapshot)?.files.values() {
+                keep.insert(entry.hash.to_owned());
+            }
+        }
+        // snapshots may have gone from the patch index too
+        self.reindex_patches()?;
+        self.store.collect_garbage(&keep)
+    }
+
+    fn patch_index_path(&self) -> path::PathBuf {
+        self.path.join("patches.json")
+    }
+
+    pub fn patch_index(&self) -> Result<PatchIndex> {
+        PatchIndex::read(&self.patch_index_path())
+    }
+
+    pub fn write_patch_index(&self, index: &PatchIndex) -> Result<()> {
+        index.write(&self.patch_index_path())
+    }
+
+    /// Index every snapshot, and the sync archive as if it were one more
+    pub fn reindex_patches(&self) -> Result<PatchIndex> {
+        let mut snapshots: Vec<(String, path::PathBuf)> = self
+            .list_snapshots()?
+            .into_iter()
+            .map(|name| {
+                let snapshot_path = self.snapshot_path(&name);
+                (name, snapshot_path)
+            })
+            .collect();
+        if self.archive_path().exists() {
+            snapshots.push(("archive".to_owned(), self.archive_path()));
+        }
+        let mut index = self.patch_index()?;
+        index.rebuild(&snapshots)?;
+        self.write_patch_index(&index)?;
+        Ok(index)
+    }
+
+    fn archive_path(&self) -> path::PathBuf {
+        self.path.join("patches")
+    }
+
+    fn sync_state_path(&self) -> path::PathBuf {
+        self.archive_path().join("sync.json")
+    }
+
+    pub fn sync_state(&self) -> Result<SyncState> {
+        SyncState::read(&self.sync_state_path())
+    }
+
+    pub fn write_sync_state(&self, state: &SyncState) -> Result<()> {
+        create_dir_all(self.archive_path())?;
+        state.write(&self.sync_state_path())
+    }
+
+    /// Two way sync of the op-1's synth and drum folders with the archive
+    pub fn sync_patches(
+        &self,
+        op1_path: &path::Path,
+        resolve: impl Fn(&Conflict) -> Winner,
+    ) -> Result<Vec<String>> {
+        let mut state = self.sync_state()?;
+        let log = sync::sync(op1_path, &self.archive_path(), &mut state, resolve)?;
+        self.write_sync_state(&state)?;
+        self.reindex_patches()?;
+        Ok(log)
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        Config::read(&self.path.join("config.json"))
+    }
+
+    /// Print a line and keep it in watch.log, with the time
+    pub fn log(&self, message: &str) -> Result<()> {
+        let line = format!("{} {}", time::stamp(time::now()), message);
+        println!("{}", line);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path.join("watch.log"))?;
+        writeln!(file, "{}", line)
     }
 
     pub fn new(disk_path: &path::PathBuf) -> Result<Disk> {
@@ -216,7 +1103,9 @@ impl Disk {
         }
 
         Ok(Disk {
+            path: disk_path.to_owned(),
             songs: SongsPath::new(songs_dir),
+            store: Store::new(disk_path.join("blobs")),
         })
     }
 }
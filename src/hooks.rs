@@ -0,0 +1,128 @@
+use super::config::Hooks;
+use super::copy::say;
+use super::metadata::Metadata;
+use serde::Serialize;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::process::{Command, Stdio};
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Event {
+    Save,
+    Export,
+    Upload,
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Event::Save => write!(f, "save"),
+            Event::Export => write!(f, "export"),
+            Event::Upload => write!(f, "upload"),
+        }
+    }
+}
+
+/// What a hook gets told, as json on stdin
+#[derive(Serialize)]
+pub struct Payload<'a> {
+    pub event: Event,
+    pub slug: &'a str,
+    pub name: &'a str,
+    pub artist: &'a str,
+    pub track: Option<u32>,
+    /// the file that was just written or sent
+    pub file: &'a str,
+    pub metadata: &'a Metadata,
+}
+
+impl Payload<'_> {
+    // the same again, for scripts that would rather not parse json
+    fn env(&self) -> Vec<(&str, String)> {
+        vec![
+            ("SAVE_OP1_EVENT", self.event.to_string()),
+            ("SAVE_OP1_SLUG", self.slug.to_owned()),
+            ("SAVE_OP1_NAME", self.name.to_owned()),
+            ("SAVE_OP1_ARTIST", self.artist.to_owned()),
+            ("SAVE_OP1_TRACK", self.track.map_or(String::new(), |track| track.to_string())),
+            ("SAVE_OP1_FILE", self.file.to_owned()),
+        ]
+    }
+}
+
+/// A freedesktop notification, over d-bus. Quietly does nothing if there's no
+/// one listening.
+pub fn notify(summary: &str, body: &str) {
+    let _ = Command::new("gdbus")
+        .args([
+            "call",
+            "--session",
+            "--dest",
+            "org.freedesktop.Notifications",
+            "--object-path",
+            "/org/freedesktop/Notifications",
+            "--method",
+            "org.freedesktop.Notifications.Notify",
+            "save-op1",
+            "0",
+            "",
+            summary,
+            body,
+            "[]",
+            "{}",
+            "5000",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+// whatever the hook prints gets passed on a line at a time, so it doesn't
+// scribble over the tui
+fn run_command(command: &str, payload: &Payload, json: &str) -> Result<bool> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .envs(payload.env())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // a hook that doesn't read stdin is allowed to close it early
+        let _ = stdin.write_all(json.as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    for output in [&output.stdout, &output.stderr] {
+        for line in String::from_utf8_lossy(output).lines() {
+            say(line);
+        }
+    }
+    Ok(output.status.success())
+}
+
+/// Run everything configured for the event. A hook failing gets a complaint,
+/// but the thing that already happened still happened.
+pub fn run(hooks: &Hooks, payload: &Payload) -> Result<()> {
+    if hooks.notify {
+        notify(&format!("{} finished", payload.event), payload.name);
+    }
+
+    let commands = match payload.event {
+        Event::Save => &hooks.save,
+        Event::Export => &hooks.export,
+        Event::Upload => &hooks.upload,
+    };
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let json = serde_json::to_string(payload).map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+    for command in commands {
+        match run_command(command, payload, &json) {
+            Ok(true) => {}
+            Ok(false) => say(&format!("the {} hook `{}` failed", payload.event, command)),
+            Err(error) => say(&format!("couldn't run the {} hook `{}`: {}", payload.event, command, error)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+/// A json file, or the default when there isn't one yet
+pub fn read<T: DeserializeOwned + Default>(file_path: &path::Path) -> Result<T> {
+    if !file_path.exists() {
+        return Ok(T::default());
+    }
+    let json = fs::read_to_string(file_path)?;
+    serde_json::from_str(&json).map_err(|error| Error::new(ErrorKind::InvalidData, error))
+}
+
+pub fn write<T: Serialize + ?Sized>(file_path: &path::Path, value: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+    fs::write(file_path, json)
+}
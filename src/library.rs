@@ -0,0 +1,202 @@
+use super::json;
+use super::operator::patch;
+use super::sync::PATCH_FOLDERS;
+use serde::{Deserialize, Serialize};
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+/// A patch as it appears across all the snapshots and the sync archive, keyed
+/// by where it lives on the op-1, like synth/user/pad.aif
+#[derive(Serialize, Deserialize)]
+pub struct IndexedPatch {
+    pub key: String,
+    pub name: String,
+    pub engine: String,
+    pub tags: Vec<String>,
+    pub snapshots: Vec<String>,
+}
+
+impl std::fmt::Display for IndexedPatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} ({}) {}", self.name, self.engine, self.key)?;
+        for tag in &self.tags {
+            write!(f, " #{}", tag)?;
+        }
+        write!(f, "\n  in {}", self.snapshots.join(", "))
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct PatchIndex {
+    pub patches: Vec<IndexedPatch>,
+}
+
+// every letter of the query, in order, with a bonus for runs and for matching
+// at the start of a word, and a point off for each letter skipped between
+// them. None if the letters aren't all there.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for letter in query.to_lowercase().chars() {
+        let found = position + text[position..].iter().position(|c| *c == letter)?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !text[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        if previous.is_some() {
+            score -= (found - position) as i64;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+impl PatchIndex {
+    pub fn read(file_path: &path::Path) -> Result<PatchIndex> {
+        json::read(file_path)
+    }
+
+    pub fn write(&self, file_path: &path::Path) -> Result<()> {
+        json::write(file_path, self)
+    }
+
+    /// Look through every snapshot again, holding on to the tags we had
+    pub fn rebuild(&mut self, snapshots: &[(String, path::PathBuf)]) -> Result<()> {
+        let mut patches: Vec<IndexedPatch> = vec![];
+        for (snapshot, snapshot_path) in snapshots {
+            for kind in &PATCH_FOLDERS {
+                let kind_path = snapshot_path.join(kind);
+                if !kind_path.exists() {
+                    continue;
+                }
+                for found in patch::list(&kind_path)? {
+                    let file_name = found.path.file_name().unwrap().to_str().unwrap();
+                    let key = format!("{}/{}/{}", kind, found.folder, file_name);
+                    match patches.iter_mut().find(|patch| patch.key == key) {
+                        Some(patch) => patch.snapshots.push(snapshot.to_owned()),
+                        None => patches.push(IndexedPatch {
+                            tags: self.tags(&key),
+                            key,
+                            name: found.name().to_owned(),
+                            engine: found.engine().to_owned(),
+                            snapshots: vec![snapshot.to_owned()],
+                        }),
+                    }
+                }
+            }
+        }
+        self.patches = patches;
+        Ok(())
+    }
+
+    fn tags(&self, key: &str) -> Vec<String> {
+        self.patches
+            .iter()
+            .find(|patch| patch.key == key)
+            .map_or(vec![], |patch| patch.tags.to_owned())
+    }
+
+    /// Add tags to the patch with this key, or failing that this name
+    pub fn tag(&mut self, key_or_name: &str, tags: &[String]) -> Result<()> {
+        let patch = match self.patches.iter().position(|patch| patch.key == key_or_name) {
+            Some(index) => &mut self.patches[index],
+            None => self
+                .patches
+                .iter_mut()
+                .find(|patch| patch.name == key_or_name)
+                .ok_or_else(|| Error::new(ErrorKind::NotFound, "no patch by that name"))?,
+        };
+        for tag in tags {
+            if !patch.tags.contains(tag) {
+                patch.tags.push(tag.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    /// Words starting with # have to be tags on the patch, and the rest are
+    /// fuzzy matched against its name. Best matches first, then shortest.
+    pub fn search(&self, query: &str) -> Vec<&IndexedPatch> {
+        let (tags, words): (Vec<&str>, Vec<&str>) =
+            query.split_whitespace().partition(|word| word.starts_with('#'));
+        let name_query = words.join(" ");
+
+        let mut found: Vec<(i64, &IndexedPatch)> = self
+            .patches
+            .iter()
+            .filter(|patch| {
+                tags.iter()
+                    .all(|tag| patch.tags.iter().any(|patch_tag| patch_tag == &tag[1..]))
+            })
+            .filter_map(|patch| {
+                let score = fuzzy_score(&name_query.replace(' ', ""), &patch.name)?;
+                Some((score, patch))
+            })
+            .collect();
+        found.sort_by(|(a, a_patch), (b, b_patch)| {
+            b.cmp(a).then(a_patch.name.len().cmp(&b_patch.name.len()))
+        });
+        found.into_iter().map(|(_, patch)| patch).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fuzzy_score, IndexedPatch, PatchIndex};
+
+    fn patch(name: &str, tags: &[&str]) -> IndexedPatch {
+        IndexedPatch {
+            key: format!("synth/user/{}.aif", name),
+            name: name.to_owned(),
+            engine: "dna".to_owned(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            snapshots: vec![],
+        }
+    }
+
+    #[test]
+    fn needs_every_letter_in_order() {
+        assert!(fuzzy_score("pad", "soft pad").is_some());
+        assert!(fuzzy_score("PAD", "Soft Pad").is_some());
+        assert!(fuzzy_score("dap", "soft pad").is_none());
+        assert!(fuzzy_score("pads", "soft pad").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+    }
+
+    #[test]
+    fn runs_and_word_starts_score_higher() {
+        let score = |text| fuzzy_score("pad", text).unwrap();
+        // where the word starts doesn't matter, only how well it matches
+        assert_eq!(score("pad"), score("soft pad"));
+        assert!(score("soft pad") > score("spade"));
+        assert!(score("spade") > score("p-a-d"));
+        assert!(score("p-a-d") > score("pxaxd"));
+        assert!(score("pad one") > score("apad"));
+    }
+
+    #[test]
+    fn search_puts_the_best_first_and_needs_the_tags() {
+        let index = PatchIndex {
+            patches: vec![
+                patch("pxaxd", &["warm"]),
+                patch("pad", &[]),
+                patch("bass", &["warm"]),
+                patch("soft pad", &["warm"]),
+            ],
+        };
+        let names = |query| -> Vec<String> {
+            index.search(query).iter().map(|patch| patch.name.to_owned()).collect()
+        };
+        assert_eq!(names("pad"), vec!["pad", "soft pad", "pxaxd"]);
+        assert_eq!(names("#warm pad"), vec!["soft pad", "pxaxd"]);
+        assert_eq!(names("soft pad"), vec!["soft pad"]);
+        assert_eq!(names("#warm"), vec!["bass", "pxaxd", "soft pad"]);
+        assert!(names("#cold").is_empty());
+    }
+}
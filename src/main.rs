@@ -1,12 +1,32 @@
 use ears::AudioController;
 use std::path;
+mod audio;
+mod cli;
+mod config;
+mod convert;
 mod copy;
+mod diff;
 mod disk;
+mod hooks;
+mod json;
+mod library;
+mod metadata;
 mod operator;
+mod reaper;
+mod release;
+mod serve;
+mod site;
 mod song;
-
+mod store;
+mod sync;
+mod time;
+mod tui;
+mod watch;
+mod waveform;
+
+use audio::{mix::TrackMix, Audio, Format};
 use disk::Disk;
-use operator::{Operator, Side};
+use operator::{Operator, Patch, Side, TapeMapping, Track};
 
 fn ask(question: &str) -> bool {
     dialoguer::Confirmation::new()
@@ -15,17 +35,24 @@ fn ask(question: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn choose_side(op1: &Operator) -> std::io::Result<&Side> {
+enum AlbumChoice<'op1> {
+    One(&'op1 Side),
+    Both,
+}
+
+fn choose_side(op1: &Operator) -> std::io::Result<AlbumChoice<'_>> {
     match dialoguer::Select::new()
         .with_prompt("Choose a side")
         .items(&[&op1.album.side_a, &op1.album.side_b])
+        .item("both sides, as an album")
         .item("back")
         .interact()
         .unwrap()
     {
-        0 => Ok(&op1.album.side_a),
-        1 => Ok(&op1.album.side_b),
-        2 => Ok(&Side::Neither),
+        0 => Ok(AlbumChoice::One(&op1.album.side_a)),
+        1 => Ok(AlbumChoice::One(&op1.album.side_b)),
+        2 => Ok(AlbumChoice::Both),
+        3 => Ok(AlbumChoice::One(&Side::Neither)),
         _ => Err(std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
             "wtf?",
@@ -36,6 +63,7 @@ fn choose_side(op1: &Operator) -> std::io::Result<&Side> {
 enum SideChoice {
     SaveWithTape(String),
     Save(String),
+    Split,
     Nothing,
 }
 
@@ -69,16 +97,17 @@ fn preview(side: &Side) -> std::io::Result<()> {
 fn ask_about_side(side: &Side) -> std::io::Result<SideChoice> {
     match dialoguer::Select::new()
         .with_prompt(format!("{}", side).as_ref())
-        .items(&["save", "preview", "back"])
+        .items(&["save", "split into songs", "preview", "back"])
         .interact()
         .unwrap()
     {
         0 => save(),
-        1 => {
+        1 => Ok(SideChoice::Split),
+        2 => {
             preview(side)?;
             ask_about_side(side)
         }
-        2 => Ok(SideChoice::Nothing),
+        3 => Ok(SideChoice::Nothing),
         _ => Err(std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
             "how did you do that?",
@@ -92,6 +121,168 @@ enum TapeOption {
     WithoutTape,
 }
 
+// the fades to trim a side with, if it wants trimming
+fn trim_menu(side: &path::Path) -> std::io::Result<Option<(f64, f64)>> {
+    let (start, end) = match audio::silence(side)? {
+        Some(silence) => silence,
+        // a silent side has nothing to trim down to
+        None => return Ok(None),
+    };
+    if start + end < 0.5 {
+        return Ok(None);
+    }
+
+    println!(
+        "there's {:.1}s of silence at the start and {:.1}s at the end",
+        start, end
+    );
+    if ask("trim it?") {
+        let fade_in: f64 = dialoguer::Input::new()
+            .with_prompt("fade in (seconds)")
+            .default(0.0)
+            .interact()?;
+        let fade_out: f64 = dialoguer::Input::new()
+            .with_prompt("fade out (seconds)")
+            .default(0.0)
+            .interact()?;
+        return Ok(Some((fade_in, fade_out)));
+    }
+    Ok(None)
+}
+
+// empty tracks are hundreds of megabytes of nothing, so they start unticked
+fn choose_tracks_to_save(op1: &Operator) -> std::io::Result<Vec<&Track>> {
+    println!("looking for empty tracks");
+    let tracks = op1.tape.tracks();
+    let empty: Vec<std::io::Result<bool>> = tracks.iter().map(|track| track.is_empty()).collect();
+    let labels: Vec<String> = tracks
+        .iter()
+        .zip(&empty)
+        .map(|(track, empty)| match empty {
+            Ok(true) => format!("{} (empty)", track),
+            Ok(false) => track.to_string(),
+            Err(error) => format!("{} (couldn't check: {})", track, error),
+        })
+        .collect();
+    // if we can't tell, better to save it than lose it
+    let defaults: Vec<bool> = empty.iter().map(|empty| !matches!(empty, Ok(true))).collect();
+
+    let chosen = dialoguer::Checkboxes::new()
+        .with_prompt("which tracks?")
+        .items(&labels)
+        .defaults(&defaults)
+        .interact()?;
+    Ok(chosen.into_iter().map(|index| tracks[index]).collect())
+}
+
+fn ask_normalize() -> std::io::Result<Option<f64>> {
+    if !ask("normalize mp3?") {
+        return Ok(None);
+    }
+    let target: f64 = dialoguer::Input::new()
+        .with_prompt("target (LUFS)")
+        .default(-14.0)
+        .interact()?;
+    Ok(Some(target))
+}
+
+fn export_song(disk: &Disk, song: &song::Song, normalize: Option<f64>) -> std::io::Result<()> {
+    println!("measuring loudness");
+    let loudness = disk.measure_loudness(song)?;
+    println!("{}", loudness);
+
+    println!("creating mp3");
+    disk.create_mp3(song, normalize)
+}
+
+fn timestamp(seconds: f64) -> String {
+    format!("{}:{:04.1}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+// 90, 1:30 and 1:30.5 are all fine
+fn parse_timestamp(string: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in string.split(':') {
+        seconds = seconds * 60.0 + part.trim().parse::<f64>().ok()?;
+    }
+    Some(seconds)
+}
+
+fn split_menu(
+    op1: &Operator,
+    disk: &Disk,
+    side_path: &path::Path,
+    artist_name: &str,
+) -> std::io::Result<()> {
+    let side = Audio::read(side_path)?;
+    let pieces = match dialoguer::Select::new()
+        .with_prompt("split")
+        .items(&["at silences", "at timestamps", "back"])
+        .interact()
+        .unwrap()
+    {
+        0 => {
+            let gap: f64 = dialoguer::Input::new()
+                .with_prompt("shortest gap (seconds)")
+                .default(2.0)
+                .interact()?;
+            audio::edit::pieces(&side, audio::edit::SILENCE, gap)
+        }
+        1 => {
+            let cuts: String = dialoguer::Input::new()
+                .with_prompt("cut at (like 1:30 4:02)")
+                .interact()?;
+            let cuts: Option<Vec<f64>> = cuts.split_whitespace().map(parse_timestamp).collect();
+            match cuts {
+                Some(cuts) => audio::edit::cut(&side, &cuts),
+                None => {
+                    println!("couldn't read those timestamps");
+                    return Ok(());
+                }
+            }
+        }
+        _ => return Ok(()),
+    };
+
+    let rate = f64::from(side.sample_rate);
+    for (index, (start, end)) in pieces.iter().enumerate() {
+        println!(
+            "{}. {} - {}",
+            index + 1,
+            timestamp(*start as f64 / rate),
+            timestamp(*end as f64 / rate)
+        );
+    }
+    if !ask(&format!("save these {} songs?", pieces.len())) {
+        return Ok(());
+    }
+
+    let normalize = ask_normalize()?;
+    let mut songs = vec![];
+    for (index, (start, end)) in pieces.into_iter().enumerate() {
+        let name: String = dialoguer::Input::new()
+            .with_prompt(&format!("name for {}", index + 1))
+            .interact()?;
+        let mut song = song::Song::new(disk, &name, artist_name);
+        song.track = Some(index as u32 + 1);
+        song.device = Some(op1.device.to_owned());
+
+        println!("writing aif");
+        disk.write_aif(&song, &audio::edit::slice(&side, start, end))?;
+        export_song(disk, &song, normalize)?;
+        disk.draw(&song.slug)?;
+        songs.push(song);
+    }
+
+    if ask("upload?") {
+        for song in &songs {
+            disk.upload_mp3(song)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn create_song(
     op1: &Operator,
     disk: &Disk,
@@ -100,24 +291,75 @@ fn create_song(
     song_name: &str,
     tape_option: TapeOption,
 ) -> std::io::Result<()> {
-    let song = song::Song::new(&disk, song_name, artist_name);
+    let mut song = song::Song::new(&disk, song_name, artist_name);
+    song.device = Some(op1.device.to_owned());
+
+    let fades = trim_menu(source)?;
+    let normalize = ask_normalize()?;
+    let tracks = match tape_option {
+        TapeOption::WithTape => choose_tracks_to_save(op1)?,
+        TapeOption::WithoutTape => vec![],
+    };
+    disk.save_song(&song, Some(source), tracks, fades, normalize)?;
 
-    println!("copying aif");
-    disk.save_aif(&song, source)?;
+    if ask("upload?") {
+        disk.upload_mp3(&song)?;
+    }
 
-    println!("creating mp3");
-    disk.create_mp3(&song)?;
+    Ok(())
+}
+
+// both sides in one go, as tracks one and two of an album
+fn save_album_menu(op1: &Operator, disk: &Disk, artist_name: &str) -> std::io::Result<()> {
+    let album_name: String = dialoguer::Input::new().with_prompt("album name").interact()?;
+    let mut songs = vec![];
+    for side in &[&op1.album.side_a, &op1.album.side_b] {
+        let side_path = match side.path() {
+            Some(path) => path,
+            None => continue,
+        };
+        match audio::is_silent(side_path) {
+            Ok(true) => {
+                println!("{} is empty, leaving it out", side);
+                continue;
+            }
+            Ok(false) => {}
+            Err(error) => println!("couldn't check if {} is empty ({}), saving it anyway", side, error),
+        }
+        let name: String = dialoguer::Input::new()
+            .with_prompt(&format!("name for {}", side))
+            .interact()?;
+        let mut song = song::Song::new(disk, &name, artist_name);
+        song.device = Some(op1.device.to_owned());
+
+        let fades = trim_menu(side_path)?;
+        println!("copying {}", side);
+        disk.save_aif(&song, side_path)?;
+        if let Some((fade_in, fade_out)) = fades {
+            disk.save_master(&song, fade_in, fade_out)?;
+        }
+        songs.push(song);
+    }
+    if songs.is_empty() {
+        return Ok(());
+    }
+    disk.save_album(&album_name, artist_name, &mut songs)?;
 
-    println!("tagging mp3");
-    disk.tag_mp3(&song)?;
+    let normalize = ask_normalize()?;
+    for song in &songs {
+        export_song(disk, song, normalize)?;
+        disk.draw(&song.slug)?;
+    }
 
-    if tape_option == TapeOption::WithTape {
-        println!("copying tape");
-        disk.save_tape(&song, op1.tape.tracks())?;
+    if ask("make a release?") {
+        let zip = disk.release(&album_name, artist_name, &mut songs, None)?;
+        println!("released {}", zip.to_str().unwrap());
     }
 
     if ask("upload?") {
-        disk.upload_mp3(&song)?;
+        for song in &songs {
+            disk.upload_mp3(song)?;
+        }
     }
 
     Ok(())
@@ -125,7 +367,10 @@ fn create_song(
 
 fn album_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
     let artist_name = "quiet party";
-    let side = choose_side(&op1)?;
+    let side = match choose_side(&op1)? {
+        AlbumChoice::One(side) => side,
+        AlbumChoice::Both => return save_album_menu(op1, disk, artist_name),
+    };
 
     let side_path = match side.path() {
         Some(path) => path,
@@ -134,6 +379,7 @@ fn album_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
 
     match ask_about_side(&side)? {
         SideChoice::Nothing => {}
+        SideChoice::Split => split_menu(op1, disk, side_path, artist_name)?,
         SideChoice::Save(name) => create_song(
             &op1,
             &disk,
@@ -154,37 +400,362 @@ fn album_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
     Ok(())
 }
 
+// only worth asking when there's more than one op-1 around
+fn choose_device(disk: &Disk) -> std::io::Result<Option<String>> {
+    let devices = disk.list_devices()?;
+    if devices.len() < 2 {
+        return Ok(None);
+    }
+    let choice = dialoguer::Select::new()
+        .with_prompt("which op-1")
+        .item("all of them")
+        .items(&devices)
+        .interact()
+        .unwrap();
+    Ok(devices.get(choice.wrapping_sub(1)).cloned())
+}
+
+fn library_menu(disk: &Disk) -> std::io::Result<()> {
+    let songs = match choose_device(disk)? {
+        Some(device) => disk.list_songs_from(&device)?,
+        None => disk.list_songs()?,
+    };
+    let listing: Vec<String> = songs
+        .iter()
+        .map(|slug| match disk.metadata(slug).ok().and_then(|m| m.loudness) {
+            Some(loudness) => format!("{}  ({})", slug, loudness),
+            None => slug.to_owned(),
+        })
+        .collect();
+    let choice = dialoguer::Select::new().items(&listing).interact().unwrap();
+    if let Some(slug) = songs.get(choice) {
+        match disk.ascii_waveform(slug, 72, 12) {
+            Ok(waveform) => println!("{}\n{}", slug, waveform),
+            Err(_) => println!("{} has no side to draw", slug),
+        }
+    }
+    Ok(())
+}
+
+// a folder that's already on the op-1, or a new one
+fn choose_folder(folders: &[String]) -> std::io::Result<String> {
+    let choice = dialoguer::Select::new()
+        .with_prompt("folder")
+        .items(folders)
+        .item("new folder")
+        .interact()
+        .unwrap();
+    match folders.get(choice) {
+        Some(folder) => Ok(folder.to_owned()),
+        None => dialoguer::Input::new().with_prompt("folder name").interact(),
+    }
+}
+
+fn kit_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
+    let songs = disk.list_songs()?;
+    let choice = dialoguer::Select::new().items(&songs).interact().unwrap();
+    let slug = match songs.get(choice) {
+        Some(slug) => slug,
+        None => return Ok(()),
+    };
+
+    let sources = disk.sources(slug);
+    if sources.is_empty() {
+        println!("{} has no audio", slug);
+        return Ok(());
+    }
+    let labels: Vec<&String> = sources.iter().map(|(label, _)| label).collect();
+    let choice = dialoguer::Select::new()
+        .with_prompt("chop up")
+        .items(&labels)
+        .interact()
+        .unwrap();
+    let source = Audio::read(&sources[choice].1)?;
+
+    let regions = match dialoguer::Select::new()
+        .with_prompt("chop")
+        .items(&["at silences", "evenly"])
+        .interact()
+        .unwrap()
+    {
+        0 => {
+            let gap: f64 = dialoguer::Input::new()
+                .with_prompt("shortest gap (seconds)")
+                .default(0.1)
+                .interact()?;
+            audio::edit::pieces(&source, audio::edit::SILENCE, gap)
+        }
+        _ => {
+            let count: usize = dialoguer::Input::new()
+                .with_prompt(&format!("how many pieces (up to {})", operator::drum::SLOTS))
+                .default(operator::drum::SLOTS)
+                .interact()?;
+            audio::edit::even(&source, count)
+        }
+    };
+    println!("{} pieces", regions.len().min(operator::drum::SLOTS));
+
+    let name: String = dialoguer::Input::new()
+        .with_prompt("kit name")
+        .default(slug.to_owned())
+        .interact()?;
+    let folder = choose_folder(&op1.drum.folders()?)?;
+    op1.drum.save_kit(&folder, &name, &source, &regions)?;
+    Ok(())
+}
+
+fn drum_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
+    match dialoguer::Select::new()
+        .items(&["browse kits", "make a kit from a song", "back"])
+        .interact()
+        .unwrap()
+    {
+        0 => browse_patches_menu(op1.drum.patches()?),
+        1 => kit_menu(op1, disk),
+        _ => Ok(()),
+    }
+}
+
+fn sampler_menu(op1: &Operator) -> std::io::Result<()> {
+    println!("(wav, flac, mp3, anything ffmpeg can read)");
+    let source: String = dialoguer::Input::new().with_prompt("file").interact()?;
+    let source = path::PathBuf::from(source);
+    let stem = source.file_stem().and_then(|stem| stem.to_str()).unwrap_or("sample");
+
+    let name: String = dialoguer::Input::new()
+        .with_prompt("patch name")
+        .default(stem.to_owned())
+        .interact()?;
+    let base_note: String = dialoguer::Input::new()
+        .with_prompt("base note")
+        .default("c4".to_owned())
+        .interact()?;
+    let folder = choose_folder(&op1.synth.folders()?)?;
+    op1.synth.save_sampler(&folder, &name, &source, &base_note)?;
+    Ok(())
+}
+
+fn browse_patches_menu(patches: Vec<Patch>) -> std::io::Result<()> {
+    if patches.is_empty() {
+        println!("no patches here");
+        return Ok(());
+    }
+    let names: Vec<String> = patches
+        .iter()
+        .map(|patch| format!("{}/{}", patch.folder, patch.name()))
+        .collect();
+
+    loop {
+        let choice = dialoguer::Select::new()
+            .items(&names)
+            .item("back")
+            .interact()
+            .unwrap();
+        let patch = match patches.get(choice) {
+            Some(patch) => patch,
+            None => return Ok(()),
+        };
+        println!("{}", patch);
+        if ask("show json?") {
+            println!("{}", serde_json::to_string_pretty(&patch.settings).unwrap());
+        }
+    }
+}
+
+fn synth_menu(op1: &Operator) -> std::io::Result<()> {
+    match dialoguer::Select::new()
+        .items(&["browse patches", "make a sampler patch from a file", "back"])
+        .interact()
+        .unwrap()
+    {
+        0 => browse_patches_menu(op1.synth.patches()?),
+        1 => sampler_menu(op1),
+        _ => Ok(()),
+    }
+}
+
+fn snapshot(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
+    let name = disk.snapshot(op1.path(), &op1.device)?;
+    println!("saved snapshot {}", name);
+    Ok(())
+}
+
+fn ask_about_conflict(conflict: &sync::Conflict) -> sync::Winner {
+    let newer = if conflict.device_modified >= conflict.archive_modified {
+        "the op-1's is newer"
+    } else {
+        "the archive's is newer"
+    };
+    println!("{} changed on both sides, {}", conflict.key, newer);
+    match dialoguer::Select::new()
+        .items(&["keep the op-1's", "keep the archive's", "leave them both"])
+        .interact()
+        .unwrap()
+    {
+        0 => sync::Winner::Device,
+        1 => sync::Winner::Archive,
+        _ => sync::Winner::Neither,
+    }
+}
+
+fn sync_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
+    let log = disk.sync_patches(op1.path(), ask_about_conflict)?;
+    if log.is_empty() {
+        println!("already in sync");
+    }
+    for line in log {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
 enum Menu {
     Album,
     Tape,
+    Synth,
+    Drum,
+    Snapshot,
+    Sync,
+    Library,
 }
 
 fn main_menu() -> std::io::Result<Menu> {
     match dialoguer::Select::new()
-        .items(&["albums", "tapes", "exit" /*"synths", "drums"*/])
+        .items(&[
+            "albums",
+            "tapes",
+            "synths",
+            "drums",
+            "snapshot everything",
+            "sync patches",
+            "library",
+            "exit",
+        ])
         .interact()
         .unwrap()
     {
         0 => Ok(Menu::Album),
         1 => Ok(Menu::Tape),
-        2 => std::process::exit(0),
+        2 => Ok(Menu::Synth),
+        3 => Ok(Menu::Drum),
+        4 => Ok(Menu::Snapshot),
+        5 => Ok(Menu::Sync),
+        6 => Ok(Menu::Library),
+        7 => std::process::exit(0),
         _ => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "sorry?")),
     }
 }
 
 fn load_tape(op1: &Operator, disk: &Disk, slug: &str) -> std::io::Result<()> {
-    op1.save_tape(disk.track_paths(slug))
+    let sources: Vec<path::PathBuf> = (1..=4).map(|number| disk.track_path(slug, number)).collect();
+    let labels: Vec<String> = sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| {
+            if source.exists() {
+                format!("track_{}", index + 1)
+            } else {
+                format!("track_{} (not saved)", index + 1)
+            }
+        })
+        .collect();
+    let defaults: Vec<bool> = sources
+        .iter()
+        .map(|source| source.exists() && !matches!(audio::is_silent(source), Ok(true)))
+        .collect();
+
+    let chosen = dialoguer::Checkboxes::new()
+        .with_prompt("which tracks?")
+        .items(&labels)
+        .defaults(&defaults)
+        .interact()?;
+
+    let mut mapping: TapeMapping = Default::default();
+    for index in chosen {
+        if sources[index].exists() {
+            mapping[index] = Some(sources[index].to_owned());
+        }
+    }
+    op1.save_tape(&mapping)
+}
+
+fn pick_tracks_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
+    let tapes = disk.list_tapes()?;
+    let tracks = ["track_1", "track_2", "track_3", "track_4"];
+    let mut mapping: TapeMapping = Default::default();
+
+    for (number, source) in mapping.iter_mut().enumerate() {
+        let choice = dialoguer::Select::new()
+            .with_prompt(&format!("what goes on track_{}?", number + 1))
+            .item("leave it alone")
+            .items(&tapes)
+            .interact()
+            .unwrap();
+        if let Some(slug) = choice.checked_sub(1).and_then(|index| tapes.get(index)) {
+            let track = dialoguer::Select::new()
+                .with_prompt(&format!("which track from {}?", slug))
+                .items(&tracks)
+                .default(number)
+                .interact()
+                .unwrap();
+            *source = Some(disk.track_path(slug, track + 1));
+        }
+    }
+
+    op1.save_tape(&mapping)
+}
+
+fn mixdown_menu(disk: &Disk, slug: &str) -> std::io::Result<()> {
+    let formats = [Format::Aiff, Format::Wav];
+    let format = formats[dialoguer::Select::new()
+        .with_prompt("format")
+        .items(&formats)
+        .interact()
+        .unwrap()];
+
+    let mut mix = vec![];
+    for track in 1..=4 {
+        let gain: f32 = dialoguer::Input::new()
+            .with_prompt(&format!("track_{} gain (dB)", track))
+            .default(0.0)
+            .interact()?;
+        let pan: f32 = dialoguer::Input::new()
+            .with_prompt(&format!("track_{} pan (-1 left, 1 right)", track))
+            .default(0.0)
+            .interact()?;
+        mix.push(TrackMix { gain, pan });
+    }
+
+    disk.mixdown(slug, &mix, format)?;
+    Ok(())
+}
+
+fn stems_menu(disk: &Disk, slug: &str) -> std::io::Result<()> {
+    let mut labels = vec![];
+    for track in 1..=4 {
+        let label: String = dialoguer::Input::new()
+            .with_prompt(&format!("track_{} label", track))
+            .default(format!("track_{}", track))
+            .interact()?;
+        labels.push(label);
+    }
+
+    let project = disk.export_stems(slug, &labels)?;
+    println!("wrote {}", project.to_str().unwrap());
+    Ok(())
 }
 
 fn load_tape_menu(op1: &Operator, disk: &Disk, slug: &str) -> std::io::Result<()> {
     match dialoguer::Select::new()
         .with_prompt(slug)
-        .items(&["write to op-1", "back"])
+        .items(&["write to op-1", "mixdown", "export stems", "back"])
         .interact()
         .unwrap()
     {
         0 => load_tape(op1, disk, slug),
-        1 => Ok(()),
+        1 => mixdown_menu(disk, slug),
+        2 => stems_menu(disk, slug),
+        3 => Ok(()),
         _ => Ok(()),
     }
 }
@@ -200,19 +771,44 @@ fn load_tapes_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
 
 fn save_tape(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
     let name: String = dialoguer::Input::new().with_prompt("name").interact()?;
-    let song = song::Song::new(&disk, &name, "quiet party");
-    disk.save_tape(&song, op1.tape.tracks())
+    let mut song = song::Song::new(&disk, &name, "quiet party");
+    song.device = Some(op1.device.to_owned());
+    disk.save_song(&song, None, choose_tracks_to_save(op1)?, None, None)
+}
+
+fn load_files_menu(op1: &Operator) -> std::io::Result<()> {
+    println!("(wav, flac, mp3, anything ffmpeg can read)");
+    let mut sources = vec![];
+    for track in 1..=4 {
+        let source: String = dialoguer::Input::new()
+            .with_prompt(&format!("file for track_{} (empty to stop)", track))
+            .allow_empty(true)
+            .interact()?;
+        if source.is_empty() {
+            break;
+        }
+        sources.push(path::PathBuf::from(source));
+    }
+    op1.load_files(&sources)
 }
 
 fn tape_menu(op1: &Operator, disk: &Disk) -> std::io::Result<()> {
     match dialoguer::Select::new()
-        .items(&["save to disk", "load to op-1", "back"])
+        .items(&[
+            "save to disk",
+            "load to op-1",
+            "pick tracks to load to op-1",
+            "load files to op-1",
+            "back",
+        ])
         .interact()
         .unwrap()
     {
         0 => save_tape(op1, disk),
         1 => load_tapes_menu(op1, disk),
-        2 => Ok(()),
+        2 => pick_tracks_menu(op1, disk),
+        3 => load_files_menu(op1),
+        4 => Ok(()),
         _ => Err(std::io::Error::new(std::io::ErrorKind::NotFound, "sorry?")),
     }?;
     Ok(())
@@ -223,13 +819,28 @@ fn main() -> std::io::Result<()> {
     // let op1_dir = path::PathBuf::from("/home/chee/Projects/save-op1/fake-op1");
     let music_dir = path::PathBuf::from("/home/chee/Documents/electronic-music/op1");
 
-    let op1 = Operator::new(&op1_dir)?;
     let disk = Disk::new(&music_dir)?;
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() {
+        return tui::run(&Operator::new(&op1_dir)?, &disk);
+    }
+    if args[0] != "menu" {
+        return cli::run(&op1_dir, &disk, &args);
+    }
+
+    // the old step by step menus, for the things the tui doesn't do yet
+    let op1 = Operator::new(&op1_dir)?;
+
     loop {
         match main_menu()? {
             Menu::Album => album_menu(&op1, &disk),
             Menu::Tape => tape_menu(&op1, &disk),
+            Menu::Synth => synth_menu(&op1),
+            Menu::Drum => drum_menu(&op1, &disk),
+            Menu::Snapshot => snapshot(&op1, &disk),
+            Menu::Sync => sync_menu(&op1, &disk),
+            Menu::Library => library_menu(&disk),
         }?;
 
         if !ask("would you like do something else?") {
@@ -0,0 +1,61 @@
+use super::audio::loudness::Loudness;
+use super::json;
+use serde::{Deserialize, Serialize};
+use std::io::Result;
+use std::path;
+
+/// How the master was cut from the original side, all in seconds
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Trim {
+    pub start: f64,
+    pub end: f64,
+    pub fade_in: f64,
+    pub fade_out: f64,
+}
+
+/// Everything we know about a song beyond its files, kept next to them as json
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    pub name: Option<String>,
+    pub artist: Option<String>,
+    pub track: Option<u32>,
+    /// The file on the op-1 the side came from, like side_a.aif
+    pub side: Option<String>,
+    /// Which op-1 it was on
+    pub device: Option<String>,
+    /// The slug of the album it's on, if it's on one
+    pub album: Option<String>,
+    pub loudness: Option<Loudness>,
+    pub trim: Option<Trim>,
+}
+
+/// Songs saved together, kept as json in the albums folder
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlbumMetadata {
+    pub name: String,
+    pub artist: String,
+    /// slugs, in track order
+    pub songs: Vec<String>,
+}
+
+impl AlbumMetadata {
+    pub fn read(file_path: &path::Path) -> Result<AlbumMetadata> {
+        json::read(file_path)
+    }
+
+    pub fn write(&self, file_path: &path::Path) -> Result<()> {
+        json::write(file_path, self)
+    }
+}
+
+impl Metadata {
+    pub fn read(file_path: &path::Path) -> Result<Metadata> {
+        json::read(file_path)
+    }
+
+    pub fn write(&self, file_path: &path::Path) -> Result<()> {
+        json::write(file_path, self)
+    }
+}
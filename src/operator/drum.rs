@@ -0,0 +1,140 @@
+use crate::audio::{aiff, Audio};
+use crate::copy::say;
+use serde::Serialize;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+/// One sample for each key on the keyboard
+pub const SLOTS: usize = 24;
+const SAMPLE_RATE: u32 = 44100;
+const MAX_SECONDS: usize = 12;
+// the op-1 doesn't count in samples, it counts in these
+const POSITION_SCALE: u64 = 4058;
+
+/// The settings the op-1 keeps in a drum kit, with everything but the
+/// sample positions left how a fresh kit has them
+#[derive(Serialize)]
+struct DrumPatch {
+    drum_version: u32,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: String,
+    octave: i32,
+    pitch: Vec<i32>,
+    start: Vec<u64>,
+    end: Vec<u64>,
+    playmode: Vec<u32>,
+    reverse: Vec<u32>,
+    volume: Vec<u32>,
+    dyna_env: Vec<u32>,
+    fx_active: bool,
+    fx_type: &'static str,
+    fx_params: Vec<u32>,
+    lfo_active: bool,
+    lfo_type: &'static str,
+    lfo_params: Vec<u32>,
+}
+
+impl DrumPatch {
+    fn new(name: &str, positions: &[(u64, u64)]) -> DrumPatch {
+        DrumPatch {
+            drum_version: 2,
+            kind: "drum",
+            name: name.to_owned(),
+            octave: 0,
+            pitch: vec![0; SLOTS],
+            start: positions.iter().map(|(start, _)| start * POSITION_SCALE).collect(),
+            end: positions.iter().map(|(_, end)| end * POSITION_SCALE).collect(),
+            playmode: vec![8192; SLOTS],
+            reverse: vec![8192; SLOTS],
+            volume: vec![8192; SLOTS],
+            dyna_env: vec![0, 8192, 0, 8192, 0, 0, 0, 0],
+            fx_active: false,
+            fx_type: "delay",
+            fx_params: vec![8000; 8],
+            lfo_active: false,
+            lfo_type: "tremolo",
+            lfo_params: vec![16000, 16000, 16000, 16000, 0, 0, 0, 0],
+        }
+    }
+}
+
+pub struct Drum {
+    path: path::PathBuf,
+}
+
+impl Drum {
+    pub fn new(path: path::PathBuf) -> Drum {
+        Drum { path }
+    }
+
+    pub fn folders(&self) -> Result<Vec<String>> {
+        super::list_folders(&self.path)
+    }
+
+    pub fn patches(&self) -> Result<Vec<super::Patch>> {
+        super::patch::list(&self.path)
+    }
+
+    /// Glue the regions of `source` together into a kit, one region per key,
+    /// and write it to drum/<folder>/<name>.aif
+    pub fn save_kit(
+        &self,
+        folder: &str,
+        name: &str,
+        source: &Audio,
+        regions: &[(usize, usize)],
+    ) -> Result<path::PathBuf> {
+        if source.sample_rate != SAMPLE_RATE {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "drum kits have to be 44.1k",
+            ));
+        }
+        let regions = &regions[..regions.len().min(SLOTS)];
+        if regions.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidInput, "nothing to put in the kit"));
+        }
+
+        // share the kit's twelve seconds out evenly if it's going to be too long
+        let max_frames = MAX_SECONDS * SAMPLE_RATE as usize;
+        let total: usize = regions.iter().map(|(start, end)| end - start).sum();
+        let budget = if total > max_frames {
+            max_frames / regions.len()
+        } else {
+            max_frames
+        };
+
+        let mono = source.mono();
+        let mut samples = vec![];
+        let mut positions = vec![];
+        for (start, end) in regions {
+            let end = (*end).min(start + budget).min(mono.len());
+            let position = samples.len() as u64;
+            samples.extend_from_slice(&mono[(*start).min(end)..end]);
+            positions.push((position, samples.len() as u64));
+        }
+        // the keys we've nothing for play nothing
+        let last = samples.len() as u64;
+        positions.resize(SLOTS, (last, last));
+
+        let kit = Audio {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits: 16,
+            float: false,
+            samples,
+        };
+        let patch = DrumPatch::new(name, &positions);
+        let json = serde_json::to_string(&patch)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+
+        let folder = self.path.join(folder);
+        fs::create_dir_all(&folder)?;
+        let target = folder.join(format!("{}.aif", slug::slugify(name)));
+        say(&format!("writing {} to op1", target.to_str().unwrap()));
+        aiff::write_with_appl(&target, &kit, Some(&super::appl_chunk(&json)))?;
+        Ok(target)
+    }
+}
@@ -0,0 +1,112 @@
+use crate::audio::aiff;
+use std::path;
+
+/// Where a particular model of op-1 keeps things, relative to where it's
+/// mounted, and what its tape sounds like
+pub trait Layout: Send + Sync {
+    fn model(&self) -> &'static str;
+    fn sides(&self) -> [&'static str; 2];
+    fn tracks(&self) -> [&'static str; 4];
+    /// the tape format to use when there's no track to ask
+    fn tape_format(&self) -> aiff::Info;
+
+    /// Whether the op-1 mounted at `mount_path` is laid out like this
+    fn check_structure(&self, mount_path: &path::Path) -> bool {
+        let exists = |relative: &str| mount_path.join(relative).exists();
+        self.sides().iter().all(|side| exists(side))
+            && self.tracks().iter().all(|track| exists(track))
+            && exists("drum")
+            && exists("synth")
+    }
+}
+
+/// The original op-1, with a mono 16 bit tape
+pub struct Original;
+
+impl Layout for Original {
+    fn model(&self) -> &'static str {
+        "op-1"
+    }
+
+    fn sides(&self) -> [&'static str; 2] {
+        ["album/side_a.aif", "album/side_b.aif"]
+    }
+
+    fn tracks(&self) -> [&'static str; 4] {
+        [
+            "tape/track_1.aif",
+            "tape/track_2.aif",
+            "tape/track_3.aif",
+            "tape/track_4.aif",
+        ]
+    }
+
+    fn tape_format(&self) -> aiff::Info {
+        aiff::Info {
+            channels: 1,
+            frames: 6 * 60 * 44100,
+            bits: 16,
+            float: false,
+            sample_rate: 44100,
+        }
+    }
+}
+
+/// The op-1 field, which puts the tape and album in folders of their own and
+/// records stereo 32 bit float
+pub struct Field;
+
+impl Layout for Field {
+    fn model(&self) -> &'static str {
+        "op-1 field"
+    }
+
+    fn sides(&self) -> [&'static str; 2] {
+        ["album/sides/side_a.aif", "album/sides/side_b.aif"]
+    }
+
+    fn tracks(&self) -> [&'static str; 4] {
+        [
+            "tape/tracks/track_1.aif",
+            "tape/tracks/track_2.aif",
+            "tape/tracks/track_3.aif",
+            "tape/tracks/track_4.aif",
+        ]
+    }
+
+    fn tape_format(&self) -> aiff::Info {
+        aiff::Info {
+            channels: 2,
+            frames: 6 * 60 * 44100,
+            bits: 32,
+            float: true,
+            sample_rate: 44100,
+        }
+    }
+}
+
+/// Work out which op-1 is mounted at `mount_path`, if it's an op-1 at all
+pub fn detect(mount_path: &path::Path) -> Option<Box<dyn Layout>> {
+    let layouts: Vec<Box<dyn Layout>> = vec![Box::new(Field), Box::new(Original)];
+    layouts
+        .into_iter()
+        .find(|layout| layout.check_structure(mount_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::detect;
+    use std::path;
+
+    fn model(fixture: &str) -> Option<&'static str> {
+        let mount = path::Path::new(env!("CARGO_MANIFEST_DIR")).join(fixture);
+        detect(&mount).map(|layout| layout.model())
+    }
+
+    #[test]
+    fn tells_the_fixtures_apart() {
+        assert_eq!(model("fake-op1"), Some("op-1"));
+        assert_eq!(model("fake-op1-field"), Some("op-1 field"));
+        assert_eq!(model("src"), None);
+    }
+}
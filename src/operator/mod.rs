@@ -1,7 +1,23 @@
-use super::copy::copy_file;
+use super::audio::{self, aiff, Audio, Format};
+use super::convert;
+use super::copy::{copy_file, list_files, say};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path;
 
+pub mod drum;
+pub mod layout;
+pub mod patch;
+pub mod synth;
+
+pub use drum::Drum;
+pub use layout::Layout;
+pub use patch::Patch;
+pub use synth::Synth;
+
+/// Everything on the op-1 that's worth keeping
+pub const FOLDERS: [&str; 4] = ["album", "tape", "synth", "drum"];
+
 #[derive(PartialEq)]
 pub enum Side {
     A(path::PathBuf),
@@ -34,17 +50,6 @@ pub struct Album {
     pub side_b: Side,
 }
 
-impl Album {
-    fn check_structure(dir_path: &path::PathBuf) -> bool {
-        let exists = |file_name: &str| -> bool {
-            path::Path::new::<str>(format!("{}/{}", dir_path.to_str().unwrap(), file_name).as_ref())
-                .exists()
-        };
-
-        exists("side_a.aif") && exists("side_b.aif")
-    }
-}
-
 pub enum Track {
     One(path::PathBuf),
     Two(path::PathBuf),
@@ -61,8 +66,16 @@ impl Track {
             Track::Four(path) => path.to_str().unwrap().to_owned(),
         }
     }
+
+    /// Nothing recorded on it, or nothing above silence anyway
+    pub fn is_empty(&self) -> std::io::Result<bool> {
+        audio::is_silent(path::Path::new(&self.path()))
+    }
 }
 
+/// A file for each of the four tracks, or None to leave that track as it is
+pub type TapeMapping = [Option<path::PathBuf>; 4];
+
 impl std::fmt::Display for Track {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match *self {
@@ -79,115 +92,237 @@ pub struct Tape {
     track_2: Track,
     track_3: Track,
     track_4: Track,
+    // for when the tracks can't tell us
+    fallback: aiff::Info,
 }
 
 impl Tape {
-    fn new(t1: path::PathBuf, t2: path::PathBuf, t3: path::PathBuf, t4: path::PathBuf) -> Tape {
+    fn new(tracks: [path::PathBuf; 4], fallback: aiff::Info) -> Tape {
+        let [t1, t2, t3, t4] = tracks;
         Tape {
             track_1: Track::One(t1),
             track_2: Track::Two(t2),
             track_3: Track::Three(t3),
             track_4: Track::Four(t4),
+            fallback,
         }
     }
 
-    pub fn save(&self, tracks: Vec<String>) -> std::io::Result<()> {
-        // lol this can't be right
-        let disk_track_1 = path::PathBuf::from(tracks.get(0).unwrap());
-        let disk_track_2 = path::PathBuf::from(tracks.get(1).unwrap());
-        let disk_track_3 = path::PathBuf::from(tracks.get(2).unwrap());
-        let disk_track_4 = path::PathBuf::from(tracks.get(3).unwrap());
-
-        let op1_track_1 = path::PathBuf::from(self.track_1.path());
-        let op1_track_2 = path::PathBuf::from(self.track_2.path());
-        let op1_track_3 = path::PathBuf::from(self.track_3.path());
-        let op1_track_4 = path::PathBuf::from(self.track_4.path());
-
-        println!("writing track_1 to op1");
-        copy_file(&disk_track_1, &op1_track_1)?;
-        println!("writing track_2 to op1");
-        copy_file(&disk_track_2, &op1_track_2)?;
-        println!("writing track_3 to op1");
-        copy_file(&disk_track_3, &op1_track_3)?;
-        println!("writing track_4 to op1");
-        copy_file(&disk_track_4, &op1_track_4)?;
+    /// Copy files onto the tape, track by track. Tracks with no file are left
+    /// alone, and anything in another format, like a tape saved from the other
+    /// kind of op-1, gets converted on the way.
+    pub fn save(&self, mapping: &TapeMapping) -> std::io::Result<()> {
+        let format = self.format();
+        for (track, source) in self.tracks().into_iter().zip(mapping) {
+            if let Some(source) = source {
+                if aiff::info(source).is_ok_and(|info| info.same_format(&format)) {
+                    say(&format!("writing {} to op1", track));
+                    copy_file(source, &path::PathBuf::from(track.path()))?;
+                } else {
+                    self.load_file(track, source)?;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// The sample rate, bit depth and length of the tape, read from track_1
+    pub fn format(&self) -> aiff::Info {
+        match aiff::info(path::Path::new(&self.track_1.path())) {
+            Ok(info) if info.frames > 0 => info,
+            _ => self.fallback,
+        }
+    }
+
+    /// Convert any old audio file to the tape's format and write it to a track
+    pub fn load_file(&self, track: &Track, source: &path::Path) -> std::io::Result<()> {
+        let format = self.format();
+        let mut audio: Audio = convert::decode(source, format.sample_rate, format.channels)?;
+        audio.bits = format.bits;
+        audio.float = format.float;
+        // the op-1 wants every track to be the whole length of the tape
+        audio.pad_to(format.frames);
+        audio
+            .samples
+            .truncate(format.frames * usize::from(format.channels));
+
+        say(&format!("writing {} to {}", source.to_str().unwrap(), track));
+        audio.write(path::Path::new(&track.path()), Format::Aiff)
+    }
+
     pub fn tracks(&self) -> Vec<&Track> {
         return vec![&self.track_1, &self.track_2, &self.track_3, &self.track_4];
     }
+}
 
-    fn check_structure(dir_path: &path::PathBuf) -> bool {
-        let exists = |file_name: &str| -> bool {
-            path::Path::new::<str>(format!("{}/{}", dir_path.to_str().unwrap(), file_name).as_ref())
-                .exists()
-        };
+/// A file on the op-1 holding the name we've given it
+const NAME_FILE: &str = "save-op1-name.txt";
+
+// the uuid of whatever filesystem is mounted at `mount_path`, by finding its
+// device in /proc/mounts and then that device in /dev/disk/by-uuid
+fn filesystem_uuid(mount_path: &path::Path) -> Option<String> {
+    let mount_path = mount_path.canonicalize().ok()?;
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    let device = mounts.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let device = fields.next()?;
+        let target = fields.next()?;
+        if path::Path::new(&target.replace("\\040", " ")) == mount_path {
+            Some(device.to_owned())
+        } else {
+            None
+        }
+    })?;
+    let device = path::Path::new(&device).canonicalize().ok()?;
+    fs::read_dir("/dev/disk/by-uuid")
+        .ok()?
+        .flatten()
+        .find(|entry| entry.path().canonicalize().ok().as_ref() == Some(&device))
+        .map(|entry| entry.file_name().to_str().unwrap().to_owned())
+}
+
+/// What to call the op-1 mounted at `mount_path`. A name we gave it wins, then
+/// the filesystem's uuid, then the name of the mount, which is usually the
+/// uuid anyway
+fn identify(mount_path: &path::Path) -> String {
+    if let Ok(name) = fs::read_to_string(mount_path.join(NAME_FILE)) {
+        if !name.trim().is_empty() {
+            return name.trim().to_owned();
+        }
+    }
+    filesystem_uuid(mount_path).unwrap_or_else(|| {
+        mount_path
+            .file_name()
+            .map_or("op-1".to_owned(), |name| name.to_str().unwrap().to_owned())
+    })
+}
 
-        exists("track_1.aif")
-            && exists("track_2.aif")
-            && exists("track_3.aif")
-            && exists("track_4.aif")
+/// The op-1 keeps its patch settings as json in an APPL chunk, after a little
+/// "op-1" signature
+fn appl_chunk(json: &str) -> Vec<u8> {
+    let mut chunk = b"op-1".to_vec();
+    chunk.extend_from_slice(json.as_bytes());
+    chunk.push(b'\n');
+    chunk
+}
+
+// the user's own folders inside synth or drum
+fn list_folders(dir_path: &path::Path) -> std::io::Result<Vec<String>> {
+    let mut names = vec![];
+    for entry in fs::read_dir(dir_path)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            names.push(entry.file_name().to_str().unwrap().to_owned());
+        }
     }
+    names.sort();
+    Ok(names)
 }
 
 pub struct Operator {
+    path: path::PathBuf,
+    /// which model it is, and so where everything is
+    pub layout: Box<dyn Layout>,
+    /// which op-1 this is, when there's more than one
+    pub device: String,
     pub album: Album,
-    // pub drum: Drum,
-    // pub synth: Synth,
+    pub drum: Drum,
+    pub synth: Synth,
     pub tape: Tape,
 }
 
 impl Operator {
-    fn check_structure(dir_path: &path::PathBuf) -> std::io::Result<bool> {
-        let mut has_album = false;
-        let mut has_tape = false;
-        let mut has_drum = false;
-        let mut has_synth = false;
-        for entry in fs::read_dir(dir_path)? {
-            let entry: fs::DirEntry = entry?;
-            let file_path = entry.path();
-            let file_type = entry.file_type()?;
-            if file_type.is_dir() {
-                match entry.file_name().to_str().unwrap() {
-                    "album" => has_album = Album::check_structure(&file_path),
-                    "drum" => has_drum = true,
-                    "tape" => has_tape = Tape::check_structure(&file_path),
-                    "synth" => has_synth = true,
-                    _ => {}
-                };
+    /// Whether a directory looks like a mounted op-1, of any model
+    pub fn check_structure(dir_path: &path::Path) -> std::io::Result<bool> {
+        if !dir_path.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "nothing mounted there",
+            ));
+        }
+        Ok(layout::detect(dir_path).is_some())
+    }
+
+    /// Where the op-1 is mounted
+    pub fn path(&self) -> &path::Path {
+        &self.path
+    }
+
+    /// Every file in `folders`, keyed by its path on the op-1
+    pub fn files(&self, folders: &[String]) -> std::io::Result<BTreeMap<String, path::PathBuf>> {
+        let mut files = BTreeMap::new();
+        for folder in folders {
+            let folder_path = self.path.join(folder);
+            let found = if folder_path.is_dir() {
+                list_files(&folder_path)?
+            } else if folder_path.exists() {
+                vec![folder_path]
+            } else {
+                vec![]
+            };
+            for file_path in found {
+                let name = file_path.strip_prefix(&self.path).unwrap();
+                files.insert(name.to_str().unwrap().to_owned(), file_path.to_owned());
             }
         }
-        return Ok(has_album && has_tape && has_drum && has_synth);
+        Ok(files)
     }
 
-    pub fn save_tape(&self, tracks: Vec<String>) -> std::io::Result<()> {
-        self.tape.save(tracks)
+    /// Give the op-1 a name, kept on the op-1 itself
+    pub fn set_name(&mut self, name: &str) -> std::io::Result<()> {
+        fs::write(self.path.join(NAME_FILE), format!("{}\n", name))?;
+        self.device = name.to_owned();
+        Ok(())
     }
 
-    pub fn new(mount_path: &path::PathBuf) -> std::io::Result<Operator> {
-        let is_valid = Operator::check_structure(mount_path)?;
-        let get_path = |suffix: &str| -> path::PathBuf {
-            path::PathBuf::from(format!("{}/{}", mount_path.to_str().unwrap(), suffix))
-        };
-        if is_valid {
-            Ok(Operator {
-                album: Album {
-                    side_a: Side::A(get_path("album/side_a.aif")),
-                    side_b: Side::B(get_path("album/side_b.aif")),
-                },
-                tape: Tape::new(
-                    get_path("tape/track_1.aif"),
-                    get_path("tape/track_2.aif"),
-                    get_path("tape/track_3.aif"),
-                    get_path("tape/track_4.aif"),
-                ),
-            })
-        } else {
-            Err(std::io::Error::new(
+    /// Every synth patch and drum kit on the op-1
+    pub fn patches(&self) -> std::io::Result<Vec<Patch>> {
+        let mut patches = self.synth.patches()?;
+        patches.extend(self.drum.patches()?);
+        Ok(patches)
+    }
+
+    pub fn save_tape(&self, mapping: &TapeMapping) -> std::io::Result<()> {
+        self.tape.save(mapping)
+    }
+
+    /// Put up to four files onto the tape, in track order
+    pub fn load_files(&self, sources: &[path::PathBuf]) -> std::io::Result<()> {
+        if sources.len() > 4 {
+            return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
-                "oh no!",
-            ))
+                "the tape only has four tracks",
+            ));
+        }
+        for (track, source) in self.tape.tracks().into_iter().zip(sources) {
+            self.tape.load_file(track, source)?;
         }
+        Ok(())
+    }
+
+    pub fn new(mount_path: &path::PathBuf) -> std::io::Result<Operator> {
+        let layout = match layout::detect(mount_path) {
+            Some(layout) => layout,
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "oh no!",
+                ))
+            }
+        };
+        let get_path = |suffix: &str| -> path::PathBuf { mount_path.join(suffix) };
+        let [side_a, side_b] = layout.sides();
+        Ok(Operator {
+            path: mount_path.to_owned(),
+            device: identify(mount_path),
+            album: Album {
+                side_a: Side::A(get_path(side_a)),
+                side_b: Side::B(get_path(side_b)),
+            },
+            drum: Drum::new(get_path("drum")),
+            synth: Synth::new(get_path("synth")),
+            tape: Tape::new(layout.tracks().map(get_path), layout.tape_format()),
+            layout,
+        })
     }
 }
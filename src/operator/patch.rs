@@ -0,0 +1,107 @@
+use crate::audio::aiff;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+/// A synth or drum patch on the op-1, and the settings from its APPL chunk
+#[derive(Serialize)]
+pub struct Patch {
+    pub path: path::PathBuf,
+    pub folder: String,
+    pub settings: Value,
+}
+
+impl Patch {
+    pub fn read(file_path: &path::Path) -> Result<Patch> {
+        let appl = aiff::read_appl(file_path)?
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "that's not an op-1 patch"))?;
+        let json = appl
+            .strip_prefix(b"op-1")
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "that's not an op-1 patch"))?;
+        // the json is followed by a newline and sometimes some padding
+        let end = json.iter().rposition(|byte| *byte == b'}').map_or(0, |end| end + 1);
+        let settings = serde_json::from_slice(&json[..end])
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+
+        let folder = file_path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .and_then(|folder| folder.to_str())
+            .unwrap_or("")
+            .to_owned();
+
+        Ok(Patch {
+            path: file_path.to_owned(),
+            folder,
+            settings,
+        })
+    }
+
+    fn setting(&self, key: &str) -> Option<&Value> {
+        self.settings.get(key)
+    }
+
+    fn text(&self, key: &str) -> &str {
+        self.setting(key).and_then(Value::as_str).unwrap_or("?")
+    }
+
+    fn active(&self, key: &str) -> bool {
+        self.setting(key).and_then(Value::as_bool).unwrap_or(false)
+    }
+
+    pub fn name(&self) -> &str {
+        match self.setting("name").and_then(Value::as_str) {
+            Some(name) => name,
+            None => self
+                .path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("?"),
+        }
+    }
+
+    /// The synth engine, or "drum" for a drum kit
+    pub fn engine(&self) -> &str {
+        self.text("type")
+    }
+
+    pub fn octave(&self) -> i64 {
+        self.setting("octave").and_then(Value::as_i64).unwrap_or(0)
+    }
+}
+
+impl std::fmt::Display for Patch {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let switch = |active: bool| if active { "on" } else { "off" };
+        writeln!(f, "{} ({}/)", self.name(), self.folder)?;
+        writeln!(f, "  engine: {}", self.engine())?;
+        writeln!(f, "  octave: {}", self.octave())?;
+        writeln!(
+            f,
+            "  fx:     {} ({})",
+            self.text("fx_type"),
+            switch(self.active("fx_active"))
+        )?;
+        write!(
+            f,
+            "  lfo:    {} ({})",
+            self.text("lfo_type"),
+            switch(self.active("lfo_active"))
+        )
+    }
+}
+
+/// Every patch in the folders under `dir_path`, skipping anything unreadable
+pub fn list(dir_path: &path::Path) -> Result<Vec<Patch>> {
+    let mut patches = vec![];
+    for folder in super::list_folders(dir_path)? {
+        let mut files: Vec<path::PathBuf> = std::fs::read_dir(dir_path.join(folder))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file_path| file_path.extension().is_some_and(|ext| ext == "aif"))
+            .collect();
+        files.sort();
+        patches.extend(files.iter().filter_map(|file_path| Patch::read(file_path).ok()));
+    }
+    Ok(patches)
+}
@@ -0,0 +1,127 @@
+use crate::audio::{aiff, edit, Audio};
+use crate::convert;
+use crate::copy::say;
+use serde::Serialize;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+const SAMPLE_RATE: u32 = 44100;
+const MAX_SECONDS: usize = 6;
+
+/// The settings the op-1 keeps in a sampler patch, left how a fresh one has
+/// them apart from the name and the note the sample plays at
+#[derive(Serialize)]
+struct SamplerPatch {
+    synth_version: u32,
+    #[serde(rename = "type")]
+    kind: &'static str,
+    name: String,
+    octave: i32,
+    base_freq: f64,
+    adsr: Vec<u32>,
+    knobs: Vec<u32>,
+    fx_active: bool,
+    fx_type: &'static str,
+    fx_params: Vec<u32>,
+    lfo_active: bool,
+    lfo_type: &'static str,
+    lfo_params: Vec<u32>,
+}
+
+impl SamplerPatch {
+    fn new(name: &str, base_freq: f64) -> SamplerPatch {
+        SamplerPatch {
+            synth_version: 2,
+            kind: "sampler",
+            name: name.to_owned(),
+            octave: 0,
+            base_freq,
+            adsr: vec![64, 10746, 32767, 10000, 4000, 64, 4000, 4000],
+            knobs: vec![0, 0, 0, 0, 0, 0, 0, 0],
+            fx_active: false,
+            fx_type: "delay",
+            fx_params: vec![8000; 8],
+            lfo_active: false,
+            lfo_type: "tremolo",
+            lfo_params: vec![16000, 16000, 16000, 16000, 0, 0, 0, 0],
+        }
+    }
+}
+
+/// The frequency of a note like "a4", "C#3" or "eb2"
+pub fn note_frequency(note: &str) -> Option<f64> {
+    let note = note.trim().to_lowercase();
+    let mut chars = note.chars();
+    let mut semitone: i32 = match chars.next()? {
+        'c' => 0,
+        'd' => 2,
+        'e' => 4,
+        'f' => 5,
+        'g' => 7,
+        'a' => 9,
+        'b' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let octave = if let Some(rest) = rest.strip_prefix('#') {
+        semitone += 1;
+        rest
+    } else if let Some(rest) = rest.strip_prefix('b') {
+        semitone -= 1;
+        rest
+    } else {
+        rest
+    };
+    let octave: i32 = octave.parse().ok()?;
+    let midi = (octave + 1) * 12 + semitone;
+    Some(440.0 * 2f64.powf(f64::from(midi - 69) / 12.0))
+}
+
+pub struct Synth {
+    path: path::PathBuf,
+}
+
+impl Synth {
+    pub fn new(path: path::PathBuf) -> Synth {
+        Synth { path }
+    }
+
+    pub fn folders(&self) -> Result<Vec<String>> {
+        super::list_folders(&self.path)
+    }
+
+    pub fn patches(&self) -> Result<Vec<super::Patch>> {
+        super::patch::list(&self.path)
+    }
+
+    /// Turn any audio file into a sampler patch at synth/<folder>/<name>.aif,
+    /// playing at its own pitch on `base_note`
+    pub fn save_sampler(
+        &self,
+        folder: &str,
+        name: &str,
+        source: &path::Path,
+        base_note: &str,
+    ) -> Result<path::PathBuf> {
+        let base_freq = note_frequency(base_note)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "that's not a note"))?;
+
+        let audio = convert::decode(source, SAMPLE_RATE, 1)?;
+        let (start, _) = edit::bounds(&audio, edit::SILENCE)
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "that file is silent"))?;
+        let mut sample: Audio = edit::slice(&audio, start, start + MAX_SECONDS * SAMPLE_RATE as usize);
+        sample.bits = 16;
+
+        let patch = SamplerPatch::new(name, base_freq);
+        let json = serde_json::to_string(&patch)
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+
+        let folder = self.path.join(folder);
+        fs::create_dir_all(&folder)?;
+        let target = folder.join(format!("{}.aif", slug::slugify(name)));
+        say(&format!("writing {} to op1", target.to_str().unwrap()));
+        aiff::write_with_appl(&target, &sample, Some(&super::appl_chunk(&json)))?;
+        Ok(target)
+    }
+}
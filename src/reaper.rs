@@ -0,0 +1,35 @@
+pub struct Stem {
+    pub name: String,
+    pub file_name: String,
+    /// seconds
+    pub length: f64,
+}
+
+/// In double quotes, which reaper and cue sheets both want, neither of them
+/// having any way to escape one
+pub fn quote(string: &str) -> String {
+    format!("\"{}\"", string.replace('"', "'"))
+}
+
+/// A reaper project with one track per stem, each starting at zero. Ableton
+/// will happily take the stems dragged in as a folder, too.
+pub fn project(sample_rate: u32, stems: &[Stem]) -> String {
+    let mut rpp = String::new();
+    rpp.push_str("<REAPER_PROJECT 0.1 \"6.0\" 0\n");
+    rpp.push_str(&format!("  SAMPLERATE {} 0 0\n", sample_rate));
+    for stem in stems {
+        rpp.push_str("  <TRACK\n");
+        rpp.push_str(&format!("    NAME {}\n", quote(&stem.name)));
+        rpp.push_str("    <ITEM\n");
+        rpp.push_str("      POSITION 0\n");
+        rpp.push_str(&format!("      LENGTH {}\n", stem.length));
+        rpp.push_str(&format!("      NAME {}\n", quote(&stem.name)));
+        rpp.push_str("      <SOURCE WAVE\n");
+        rpp.push_str(&format!("        FILE {}\n", quote(&stem.file_name)));
+        rpp.push_str("      >\n");
+        rpp.push_str("    >\n");
+        rpp.push_str("  >\n");
+    }
+    rpp.push_str(">\n");
+    rpp
+}
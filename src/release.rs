@@ -0,0 +1,79 @@
+use super::reaper::quote;
+use super::time;
+
+pub struct ReleaseTrack {
+    pub name: String,
+    /// seconds into the whole album
+    pub start: f64,
+    /// seconds
+    pub length: f64,
+}
+
+// cue sheets count in minutes, seconds and frames, at 75 frames a second
+fn cue_time(seconds: f64) -> String {
+    let frames = (seconds * 75.0).round() as u64;
+    format!("{:02}:{:02}:{:02}", frames / 75 / 60, frames / 75 % 60, frames % 75)
+}
+
+/// A cue sheet for the whole album as one wav
+pub fn cue_sheet(title: &str, artist: &str, file_name: &str, tracks: &[ReleaseTrack]) -> String {
+    let mut cue = String::new();
+    cue.push_str(&format!("PERFORMER {}\n", quote(artist)));
+    cue.push_str(&format!("TITLE {}\n", quote(title)));
+    cue.push_str(&format!("FILE {} WAVE\n", quote(file_name)));
+    for (index, track) in tracks.iter().enumerate() {
+        cue.push_str(&format!("  TRACK {:02} AUDIO\n", index + 1));
+        cue.push_str(&format!("    TITLE {}\n", quote(&track.name)));
+        cue.push_str(&format!("    PERFORMER {}\n", quote(artist)));
+        cue.push_str(&format!("    INDEX 01 {}\n", cue_time(track.start)));
+    }
+    cue
+}
+
+/// The tracklist, for humans
+pub fn tracklist(title: &str, artist: &str, tracks: &[ReleaseTrack]) -> String {
+    let mut text = format!("{} - {}\n\n", artist, title);
+    for (index, track) in tracks.iter().enumerate() {
+        text.push_str(&format!("{}. {} ({})\n", index + 1, track.name, time::minutes(track.length)));
+    }
+    let total: f64 = tracks.iter().map(|track| track.length).sum();
+    text.push_str(&format!("\n{} total\n", time::minutes(total)));
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cue_sheet, cue_time, ReleaseTrack};
+
+    #[test]
+    fn cue_times_are_minutes_seconds_and_frames() {
+        assert_eq!(cue_time(0.0), "00:00:00");
+        assert_eq!(cue_time(1.0), "00:01:00");
+        assert_eq!(cue_time(1.2), "00:01:15");
+        assert_eq!(cue_time(61.0 + 74.0 / 75.0), "01:01:74");
+        // a hair under a second rounds up to it rather than to frame 75
+        assert_eq!(cue_time(0.9999), "00:01:00");
+        assert_eq!(cue_time(3600.0), "60:00:00");
+    }
+
+    #[test]
+    fn cue_sheets_quote_without_escaping() {
+        let tracks = [
+            ReleaseTrack {
+                name: "the \"first\" one".to_owned(),
+                start: 0.0,
+                length: 90.0,
+            },
+            ReleaseTrack {
+                name: "two".to_owned(),
+                start: 90.5,
+                length: 10.0,
+            },
+        ];
+        let cue = cue_sheet("album", "quiet party", "album.wav", &tracks);
+        assert!(cue.contains("FILE \"album.wav\" WAVE\n"));
+        assert!(cue.contains("  TRACK 01 AUDIO\n    TITLE \"the 'first' one\"\n"));
+        assert!(cue.contains("  TRACK 02 AUDIO\n"));
+        assert!(cue.contains("    INDEX 01 01:30:38\n"));
+    }
+}
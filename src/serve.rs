@@ -0,0 +1,343 @@
+use super::disk::Disk;
+use super::operator::Operator;
+use super::site::escape;
+use super::song::Song;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom};
+use std::net::SocketAddr;
+use std::path;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+}
+
+fn content_type(file_path: &path::Path) -> &'static str {
+    match file_path.extension().and_then(|extension| extension.to_str()) {
+        Some("mp3") => "audio/mpeg",
+        Some("aif") => "audio/aiff",
+        Some("wav") => "audio/wav",
+        Some("png") => "image/png",
+        Some("json") => "application/json",
+        Some("rpp") => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+// %20 back into a space and so on
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = vec![];
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' if index + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[index + 1..index + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        decoded.push(byte);
+                        index += 2;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// forms send spaces as +, and a real + as %2B
+fn decode_form(text: &str) -> String {
+    decode(&text.replace('+', " "))
+}
+
+// a form post, as name/value pairs
+fn form(request: &mut Request) -> Result<Vec<(String, String)>> {
+    let mut body = String::new();
+    request.as_reader().read_to_string(&mut body)?;
+    Ok(body
+        .split('&')
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            Some((decode_form(parts.next()?), decode_form(parts.next().unwrap_or(""))))
+        })
+        .collect())
+}
+
+fn field<'a>(form: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    form.iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_ref())
+}
+
+fn page(disk: &Disk, message: &str) -> Result<String> {
+    let mut html = String::new();
+    html.push_str("<!doctype html>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width\">\n");
+    html.push_str("<title>save-op1</title>\n<h1>save-op1</h1>\n");
+    if !message.is_empty() {
+        html.push_str(&format!("<p><b>{}</b></p>\n", escape(message)));
+    }
+
+    html.push_str("<h2>op-1</h2>\n<form method=\"post\" action=\"/save\">\n");
+    html.push_str("  <select name=\"side\"><option value=\"a\">side a</option>");
+    html.push_str("<option value=\"b\">side b</option></select>\n");
+    html.push_str("  <input name=\"name\" placeholder=\"name\" required>\n");
+    html.push_str("  <label><input type=\"checkbox\" name=\"tape\"> bring tape</label>\n");
+    html.push_str("  <button>save</button>\n</form>\n");
+    html.push_str("<form method=\"post\" action=\"/snapshot\"><button>snapshot everything</button></form>\n");
+
+    let mut songs = disk.list_songs()?;
+    songs.sort();
+    html.push_str("<h2>songs</h2>\n");
+    for slug in &songs {
+        html.push_str(&format!(
+            "<details id=\"{}\">\n  <summary>{}</summary>\n",
+            escape(slug),
+            escape(slug)
+        ));
+        for file_name in disk.song_files(slug)? {
+            let url = escape(&format!("/songs/{}/{}", slug, file_name));
+            let is_audio = [".mp3", ".wav", ".aif"]
+                .iter()
+                .any(|extension| file_name.ends_with(extension));
+            if is_audio {
+                html.push_str(&format!("  <audio controls preload=\"none\" src=\"{}\"></audio>\n", url));
+            }
+            html.push_str(&format!(
+                "  <a href=\"{}\" download>{}</a><br>\n",
+                url,
+                escape(&file_name)
+            ));
+        }
+        html.push_str("</details>\n");
+    }
+
+    html.push_str("<h2>tapes</h2>\n");
+    for slug in disk.list_tapes()? {
+        html.push_str(&format!("<details>\n  <summary>{}</summary>\n", escape(&slug)));
+        for number in 1..=4 {
+            let file_name = format!("tape/track_{}.aif", number);
+            if disk.song_file(&slug, &file_name).is_none() {
+                continue;
+            }
+            let url = escape(&format!("/songs/{}/{}", slug, file_name));
+            html.push_str(&format!("  <p>track {}</p>\n", number));
+            html.push_str(&format!("  <audio controls preload=\"none\" src=\"{}\"></audio>\n", url));
+            html.push_str(&format!("  <a href=\"{}\" download>track_{}.aif</a><br>\n", url, number));
+        }
+        html.push_str("</details>\n");
+    }
+    Ok(html)
+}
+
+fn html(body: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    Response::from_string(body).with_header(header("Content-Type", "text/html; charset=utf-8"))
+}
+
+// bytes=start-end, bytes=start- or bytes=-how_many_from_the_end, which is
+// what audio players send to seek
+fn range(value: &str, length: u64) -> Option<(u64, u64)> {
+    let value = value.strip_prefix("bytes=")?;
+    if let Some(suffix) = value.strip_prefix('-') {
+        let suffix: u64 = suffix.parse().ok()?;
+        if suffix == 0 {
+            return None;
+        }
+        return Some((length.saturating_sub(suffix), length.checked_sub(1)?));
+    }
+    let mut parts = value.splitn(2, '-');
+    let start: u64 = parts.next()?.parse().ok()?;
+    let end: u64 = match parts.next()? {
+        "" => length.checked_sub(1)?,
+        end => end.parse::<u64>().ok()?.min(length.checked_sub(1)?),
+    };
+    if start > end {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn send_file(request: Request, file_path: &path::Path) -> Result<()> {
+    let mut file = File::open(file_path)?;
+    let length = file.metadata()?.len();
+    let kind = header("Content-Type", content_type(file_path));
+    let ranges = header("Accept-Ranges", "bytes");
+    let wanted = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Range"))
+        .and_then(|header| range(header.value.as_str(), length));
+    match wanted {
+        Some((start, end)) => {
+            file.seek(SeekFrom::Start(start))?;
+            let size = (end - start + 1) as usize;
+            let response = Response::new(
+                StatusCode(206),
+                vec![
+                    kind,
+                    ranges,
+                    header("Content-Range", &format!("bytes {}-{}/{}", start, end, length)),
+                ],
+                file.take(size as u64),
+                Some(size),
+                None,
+            );
+            request.respond(response)
+        }
+        None => request.respond(
+            Response::from_file(file)
+                .with_header(kind)
+                .with_header(ranges),
+        ),
+    }
+}
+
+fn save(op1_dir: &path::PathBuf, disk: &Disk, form: &[(String, String)]) -> Result<String> {
+    let op1 = Operator::new(op1_dir)?;
+    let side = match field(form, "side") {
+        Some("b") => &op1.album.side_b,
+        _ => &op1.album.side_a,
+    };
+    let name = field(form, "name")
+        .filter(|name| !name.trim().is_empty())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "songs need names"))?;
+
+    let side_path = side
+        .path()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "that side isn't on the op-1"))?;
+
+    let mut song = Song::new(disk, name.trim(), &disk.config()?.artist);
+    song.device = Some(op1.device.to_owned());
+    let tracks = match field(form, "tape") {
+        Some(_) => op1.tape.tracks().into_iter().filter(|track| !matches!(track.is_empty(), Ok(true))).collect(),
+        None => vec![],
+    };
+    disk.save_song(&song, Some(side_path), tracks, None, None)?;
+    Ok(format!("saved {} as {}", side, song.slug))
+}
+
+// saving and snapshots take a while, so they get a thread each rather than
+// holding up every other request
+fn in_background<'scope, 'env>(
+    scope: &'scope thread::Scope<'scope, 'env>,
+    disk: &'env Disk,
+    request: Request,
+    job: impl FnOnce() -> String + Send + 'scope,
+) {
+    scope.spawn(move || {
+        let message = job();
+        let response = page(disk, &message).and_then(|body| request.respond(html(body)));
+        if let Err(error) = response {
+            println!("that went wrong: {}", error);
+        }
+    });
+}
+
+fn handle<'scope, 'env>(
+    scope: &'scope thread::Scope<'scope, 'env>,
+    op1_dir: &'env path::PathBuf,
+    disk: &'env Disk,
+    mut request: Request,
+) -> Result<()> {
+    let url = decode(request.url().split('?').next().unwrap_or("/"));
+    println!("{} {}", request.method(), url);
+
+    let parts: Vec<&str> = url.trim_start_matches('/').splitn(3, '/').collect();
+    match (request.method(), parts.as_slice()) {
+        (Method::Get, [""]) => {
+            let body = page(disk, "")?;
+            request.respond(html(body))
+        }
+        (Method::Get, ["songs", slug, file_name]) => match disk.song_file(slug, file_name) {
+            Some(file_path) => send_file(request, &file_path),
+            None => request.respond(Response::from_string("not here").with_status_code(404)),
+        },
+        (Method::Post, ["save"]) => {
+            let form = form(&mut request)?;
+            in_background(scope, disk, request, move || {
+                save(op1_dir, disk, &form).unwrap_or_else(|error| format!("oh no: {}", error))
+            });
+            Ok(())
+        }
+        (Method::Post, ["snapshot"]) => {
+            in_background(scope, disk, request, move || match Operator::new(op1_dir) {
+                Ok(op1) => match disk.snapshot(op1.path(), &op1.device) {
+                    Ok(name) => format!("saved snapshot {}", name),
+                    Err(error) => format!("oh no: {}", error),
+                },
+                Err(_) => "there's no op-1 plugged in".to_owned(),
+            });
+            Ok(())
+        }
+        _ => request.respond(Response::from_string("not here").with_status_code(404)),
+    }
+}
+
+/// A little web page of everything on the disk. It's only for this computer
+/// unless it's given an address on the network, like with --lan, and then
+/// anyone on the network can use it, since there's no password.
+pub fn serve(op1_dir: &path::PathBuf, disk: &Disk, address: &str) -> Result<()> {
+    let server = Server::http(address).map_err(|error| Error::new(ErrorKind::AddrInUse, error.to_string()))?;
+    println!("listening on http://{}", address);
+    let local = address
+        .parse::<SocketAddr>()
+        .is_ok_and(|address| address.ip().is_loopback());
+    if !local {
+        println!("anyone on the network can save and download songs from here, there's no password");
+    }
+    thread::scope(|scope| {
+        for request in server.incoming_requests() {
+            if let Err(error) = handle(scope, op1_dir, disk, request) {
+                println!("that went wrong: {}", error);
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode, decode_form, range};
+
+    #[test]
+    fn ranges() {
+        assert_eq!(range("bytes=0-99", 1000), Some((0, 99)));
+        assert_eq!(range("bytes=500-", 1000), Some((500, 999)));
+        assert_eq!(range("bytes=900-5000", 1000), Some((900, 999)));
+        assert_eq!(range("bytes=-100", 1000), Some((900, 999)));
+        assert_eq!(range("bytes=-5000", 1000), Some((0, 999)));
+    }
+
+    #[test]
+    fn ranges_that_make_no_sense_get_the_whole_file() {
+        assert_eq!(range("bytes=-0", 1000), None);
+        assert_eq!(range("bytes=99-0", 1000), None);
+        assert_eq!(range("bytes=0-99", 0), None);
+        assert_eq!(range("bytes=-10", 0), None);
+        assert_eq!(range("bytes=a-b", 1000), None);
+        assert_eq!(range("0-99", 1000), None);
+        assert_eq!(range("bytes=0", 1000), None);
+    }
+
+    #[test]
+    fn decoding() {
+        assert_eq!(decode("/songs/test%20song/a.mp3"), "/songs/test song/a.mp3");
+        assert_eq!(decode("caf%C3%A9"), "café");
+        // a plus in a path is just a plus
+        assert_eq!(decode("a+b"), "a+b");
+        assert_eq!(decode("100%"), "100%");
+        assert_eq!(decode("%zz%2"), "%zz%2");
+        assert_eq!(decode("%41"), "A");
+    }
+
+    #[test]
+    fn decoding_forms() {
+        assert_eq!(decode_form("new+song"), "new song");
+        assert_eq!(decode_form("c%2B%2B+song"), "c++ song");
+        assert_eq!(decode_form("%26%3D"), "&=");
+    }
+}
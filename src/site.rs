@@ -0,0 +1,103 @@
+use super::config::Site;
+use super::time;
+
+/// A song as it appears on the site
+pub struct SiteSong {
+    pub slug: String,
+    pub name: String,
+    pub artist: String,
+    pub album: Option<String>,
+    /// file names, relative to the site
+    pub mp3: String,
+    pub picture: Option<String>,
+    /// bytes
+    pub size: u64,
+    /// seconds since 1970
+    pub published: u64,
+    /// seconds
+    pub length: f64,
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// the site's url with a file on the end
+fn link(site: &Site, file_name: &str) -> String {
+    format!("{}/{}", site.url.trim_end_matches('/'), file_name)
+}
+
+/// index.html, newest first, with a player for each song
+pub fn index(site: &Site, songs: &[SiteSong]) -> String {
+    let mut html = String::new();
+    html.push_str("<!doctype html>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<meta name=\"viewport\" content=\"width=device-width\">\n");
+    html.push_str(&format!("<title>{}</title>\n", escape(&site.title)));
+    if !site.url.is_empty() {
+        html.push_str("<link rel=\"alternate\" type=\"application/rss+xml\" href=\"feed.xml\">\n");
+    }
+    html.push_str(&format!("<h1>{}</h1>\n", escape(&site.title)));
+    if !site.description.is_empty() {
+        html.push_str(&format!("<p>{}</p>\n", escape(&site.description)));
+    }
+    for song in songs {
+        html.push_str(&format!("<article id=\"{}\">\n", escape(&song.slug)));
+        html.push_str(&format!("  <h2>{}</h2>\n", escape(&song.name)));
+        let mut about = format!("{}, {}", song.artist, time::minutes(song.length));
+        if let Some(album) = &song.album {
+            about = format!("{}, from {}", about, album);
+        }
+        html.push_str(&format!("  <p>{}</p>\n", escape(&about)));
+        if let Some(picture) = &song.picture {
+            html.push_str(&format!(
+                "  <img src=\"{}\" alt=\"\" width=\"250\" height=\"250\">\n",
+                escape(picture)
+            ));
+        }
+        html.push_str(&format!(
+            "  <audio controls preload=\"none\" src=\"{}\"></audio>\n",
+            escape(&song.mp3)
+        ));
+        html.push_str(&format!("  <a href=\"{}\" download>download</a>\n", escape(&song.mp3)));
+        html.push_str("</article>\n");
+    }
+    html
+}
+
+/// feed.xml, an rss feed with the mp3s as enclosures, which is all a podcast is.
+/// Feeds need whole urls, so there's no feed until the site has a url
+pub fn feed(site: &Site, songs: &[SiteSong]) -> Option<String> {
+    if site.url.is_empty() {
+        return None;
+    }
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\">\n");
+    xml.push_str("<channel>\n");
+    xml.push_str(&format!("  <title>{}</title>\n", escape(&site.title)));
+    xml.push_str(&format!("  <link>{}</link>\n", escape(&link(site, ""))));
+    xml.push_str(&format!("  <description>{}</description>\n", escape(&site.description)));
+    for song in songs {
+        xml.push_str("  <item>\n");
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&song.name)));
+        xml.push_str(&format!("    <itunes:author>{}</itunes:author>\n", escape(&song.artist)));
+        xml.push_str(&format!("    <guid isPermaLink=\"false\">{}</guid>\n", escape(&song.slug)));
+        xml.push_str(&format!(
+            "    <link>{}</link>\n",
+            escape(&link(site, &format!("#{}", song.slug)))
+        ));
+        xml.push_str(&format!("    <pubDate>{}</pubDate>\n", time::rfc2822(song.published)));
+        xml.push_str(&format!(
+            "    <enclosure url=\"{}\" length=\"{}\" type=\"audio/mpeg\"/>\n",
+            escape(&link(site, &song.mp3)),
+            song.size
+        ));
+        xml.push_str(&format!("    <itunes:duration>{}</itunes:duration>\n", time::minutes(song.length)));
+        xml.push_str("  </item>\n");
+    }
+    xml.push_str("</channel>\n</rss>\n");
+    Some(xml)
+}
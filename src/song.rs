@@ -5,6 +5,9 @@ pub struct Song<'disk> {
     pub name: String,
     pub slug: String,
     pub artist: String,
+    pub track: Option<u32>,
+    /// the op-1 it came from
+    pub device: Option<String>,
 }
 
 impl Song<'_> {
@@ -14,6 +17,8 @@ impl Song<'_> {
             name: name.to_owned(),
             slug: slug::slugify(name),
             artist: artist_name.to_owned(),
+            track: None,
+            device: None,
         }
     }
 
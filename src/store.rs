@@ -0,0 +1,166 @@
+use super::copy::Meter;
+use super::json;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{copy, Error, ErrorKind, Result};
+use std::path;
+use tee_readwrite::TeeWriter;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn hash_file(file_path: &path::Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    copy(&mut File::open(file_path)?, &mut hasher)?;
+    Ok(hex(&hasher.finalize()))
+}
+
+// copy_file, but hashing everything on the way past
+fn copy_hashed(source: &path::Path, target: &path::Path) -> Result<String> {
+    let mut source_file = File::open(source)?;
+    let bytes = source_file.metadata()?.len();
+    let mut meter = Meter::new(source, bytes);
+    let mut target = File::create(target)?;
+    let mut hasher = Sha256::new();
+    {
+        let mut watchers = TeeWriter::new(&mut meter, &mut hasher);
+        let mut tee = TeeWriter::new(&mut target, &mut watchers);
+        copy(&mut source_file, &mut tee)?;
+    }
+    meter.finish();
+    Ok(hex(&hasher.finalize()))
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub hash: String,
+    pub size: u64,
+    /// seconds since 1970, as the op-1 reported it
+    pub modified: u64,
+}
+
+/// What was in a snapshot, by path relative to the op-1
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Manifest {
+    /// which op-1 it was taken from
+    pub device: Option<String>,
+    pub files: BTreeMap<String, Entry>,
+}
+
+impl Manifest {
+    pub fn read(file_path: &path::Path) -> Result<Manifest> {
+        json::read(file_path)
+    }
+
+    pub fn write(&self, file_path: &path::Path) -> Result<()> {
+        json::write(file_path, self)
+    }
+}
+
+/// Files kept once each, named by their sha256, and linked into snapshots
+pub struct Store {
+    path: path::PathBuf,
+}
+
+impl Store {
+    pub fn new(path: path::PathBuf) -> Store {
+        Store { path }
+    }
+
+    fn blob(&self, hash: &str) -> path::PathBuf {
+        self.path.join(hash)
+    }
+
+    pub fn has(&self, hash: &str) -> bool {
+        self.blob(hash).exists()
+    }
+
+    /// Copy a file in unless we've got it already, and say what it's called
+    pub fn add(&self, source: &path::Path) -> Result<String> {
+        fs::create_dir_all(&self.path)?;
+        let incoming = self.path.join(format!(".incoming-{}", std::process::id()));
+        let hash = copy_hashed(source, &incoming)?;
+        if self.has(&hash) {
+            fs::remove_file(&incoming)?;
+        } else {
+            fs::rename(&incoming, self.blob(&hash))?;
+        }
+        Ok(hash)
+    }
+
+    /// Put a blob at `target`, as a hard link unless it's on another disk.
+    /// The blob is made read-only first, because writing to any one link would
+    /// change the file in every snapshot sharing it. That goes for copies of
+    /// it made with `cp -p` too, but not ones made with `copy_file`.
+    pub fn link(&self, hash: &str, target: &path::Path) -> Result<()> {
+        // it could be a link to this very blob, and copying over that empties it
+        if target.symlink_metadata().is_ok() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("there's already a file at {}", target.to_str().unwrap()),
+            ));
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut permissions = fs::metadata(self.blob(hash))?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(self.blob(hash), permissions)?;
+        match fs::hard_link(self.blob(hash), target) {
+            Err(error) if error.kind() == ErrorKind::CrossesDevices => {
+                fs::copy(self.blob(hash), target).map(|_| ())
+            }
+            result => result,
+        }
+    }
+
+    /// Delete every blob not in `keep`, returning how many and how many bytes
+    pub fn collect_garbage(&self, keep: &HashSet<String>) -> Result<(usize, u64)> {
+        let mut count = 0;
+        let mut bytes = 0;
+        if !self.path.exists() {
+            return Ok((count, bytes));
+        }
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            let hash = entry.file_name().to_str().unwrap().to_owned();
+            if !keep.contains(&hash) {
+                bytes += entry.metadata()?.len();
+                fs::remove_file(entry.path())?;
+                count += 1;
+            }
+        }
+        Ok((count, bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Store;
+    use std::fs;
+
+    #[test]
+    fn linking_twice_leaves_the_blob_alone() {
+        let dir = std::env::temp_dir().join(format!("save-op1-store-{}", std::process::id()));
+        let source = dir.join("pad.aif");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&source, b"a patch").unwrap();
+
+        let store = Store::new(dir.join("blobs"));
+        let hash = store.add(&source).unwrap();
+        let target = dir.join("snapshot").join("pad.aif");
+        store.link(&hash, &target).unwrap();
+        assert!(fs::metadata(&target).unwrap().permissions().readonly());
+
+        assert!(store.link(&hash, &target).is_err());
+        assert_eq!(fs::read(&target).unwrap(), b"a patch");
+        assert_eq!(fs::read(dir.join("blobs").join(&hash)).unwrap(), b"a patch");
+
+        // read-only files can still be removed from a writable folder
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
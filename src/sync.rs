@@ -0,0 +1,353 @@
+use super::copy::{copy_file, list_files};
+use super::json;
+use super::store::hash_file;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+
+/// The folders that get synced, the rest of the op-1 is for snapshots
+pub const PATCH_FOLDERS: [&str; 2] = ["synth", "drum"];
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Synced {
+    /// what the file was the last time both sides agreed
+    pub hash: String,
+    /// whether it should be put back on the op-1 when it's missing there,
+    /// which is only ever true for things marked with `sync mark`
+    pub on_device: bool,
+    /// not on the op-1 any more, and already said so if it ever was
+    #[serde(default)]
+    pub removed: bool,
+}
+
+/// What the archive looked like after the last sync, by path on the op-1
+#[derive(Default, Serialize, Deserialize)]
+pub struct SyncState {
+    pub files: BTreeMap<String, Synced>,
+}
+
+impl SyncState {
+    pub fn read(file_path: &path::Path) -> Result<SyncState> {
+        json::read(file_path)
+    }
+
+    pub fn write(&self, file_path: &path::Path) -> Result<()> {
+        json::write(file_path, self)
+    }
+
+    pub fn mark(&mut self, key: &str, on_device: bool) -> Result<()> {
+        match self.files.get_mut(key) {
+            Some(synced) => {
+                synced.on_device = on_device;
+                Ok(())
+            }
+            None => Err(Error::new(ErrorKind::NotFound, "that patch isn't in the archive")),
+        }
+    }
+}
+
+/// Both sides changed since the last sync
+pub struct Conflict {
+    pub key: String,
+    /// seconds since 1970
+    pub device_modified: u64,
+    pub archive_modified: u64,
+}
+
+pub enum Winner {
+    Device,
+    Archive,
+    Neither,
+}
+
+pub enum Policy {
+    Device,
+    Archive,
+    Newer,
+}
+
+impl Policy {
+    pub fn parse(name: &str) -> Option<Policy> {
+        match name {
+            "device" => Some(Policy::Device),
+            "archive" => Some(Policy::Archive),
+            "newer" => Some(Policy::Newer),
+            _ => None,
+        }
+    }
+
+    pub fn resolve(&self, conflict: &Conflict) -> Winner {
+        match self {
+            Policy::Device => Winner::Device,
+            Policy::Archive => Winner::Archive,
+            Policy::Newer if conflict.device_modified >= conflict.archive_modified => Winner::Device,
+            Policy::Newer => Winner::Archive,
+        }
+    }
+}
+
+fn modified(file_path: &path::Path) -> Result<u64> {
+    Ok(file_path
+        .metadata()?
+        .modified()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs()))
+}
+
+fn files(root: &path::Path) -> Result<BTreeMap<String, path::PathBuf>> {
+    let mut files = BTreeMap::new();
+    for folder in &PATCH_FOLDERS {
+        let folder_path = root.join(folder);
+        if !folder_path.exists() {
+            continue;
+        }
+        for file_path in list_files(&folder_path)? {
+            let key = file_path.strip_prefix(root).unwrap().to_str().unwrap().to_owned();
+            files.insert(key, file_path);
+        }
+    }
+    Ok(files)
+}
+
+fn copy_over(source: &path::PathBuf, target: &path::PathBuf) -> Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    copy_file(source, target)
+}
+
+/// Sync the synth and drum folders on the op-1 with the archive. New things
+/// on the op-1 go in the archive, things marked on_device go back on the
+/// op-1, anything else deleted on the op-1 stays in the archive and gets
+/// mentioned, and when both sides changed `resolve` picks which one wins.
+/// Returns what happened, a line for each file that moved.
+pub fn sync(
+    op1_path: &path::Path,
+    archive_path: &path::Path,
+    state: &mut SyncState,
+    resolve: impl Fn(&Conflict) -> Winner,
+) -> Result<Vec<String>> {
+    let device = files(op1_path)?;
+    let archive = files(archive_path)?;
+    let keys: BTreeSet<&String> = device.keys().chain(archive.keys()).collect();
+    let mut log = vec![];
+
+    for key in keys {
+        let device_path = op1_path.join(key);
+        let archive_path = archive_path.join(key);
+        let synced = state.files.get(key).cloned();
+        let on_device = synced.as_ref().is_some_and(|synced| synced.on_device);
+        let mut removed = false;
+
+        let pull = |log: &mut Vec<String>| -> Result<String> {
+            copy_over(&device_path, &archive_path)?;
+            log.push(format!("op-1 -> archive {}", key));
+            hash_file(&archive_path)
+        };
+        let push = |log: &mut Vec<String>| -> Result<String> {
+            copy_over(&archive_path, &device_path)?;
+            log.push(format!("archive -> op-1 {}", key));
+            hash_file(&device_path)
+        };
+
+        let hash = match (device.contains_key(key), archive.contains_key(key)) {
+            (true, false) => pull(&mut log)?,
+            (false, true) if on_device => push(&mut log)?,
+            (false, true) => {
+                if synced.as_ref().is_some_and(|synced| !synced.removed) {
+                    log.push(format!("deleted on the op-1, kept in the archive {}", key));
+                }
+                // anything only ever in the archive has nothing to mention
+                removed = true;
+                hash_file(&archive_path)?
+            }
+            (false, false) => continue,
+            (true, true) => {
+                let device_hash = hash_file(&device_path)?;
+                let archive_hash = hash_file(&archive_path)?;
+                let base = synced.as_ref().map(|synced| synced.hash.to_owned());
+                if device_hash == archive_hash {
+                    device_hash
+                } else if base.as_ref() == Some(&archive_hash) {
+                    pull(&mut log)?
+                } else if base.as_ref() == Some(&device_hash) {
+                    push(&mut log)?
+                } else {
+                    let conflict = Conflict {
+                        key: key.to_owned(),
+                        device_modified: modified(&device_path)?,
+                        archive_modified: modified(&archive_path)?,
+                    };
+                    match resolve(&conflict) {
+                        Winner::Device => pull(&mut log)?,
+                        Winner::Archive => push(&mut log)?,
+                        Winner::Neither => {
+                            log.push(format!("left alone {}", key));
+                            continue;
+                        }
+                    }
+                }
+            }
+        };
+
+        state.files.insert(
+            key.to_owned(),
+            Synced {
+                hash,
+                on_device,
+                removed,
+            },
+        );
+    }
+
+    Ok(log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sync, Conflict, SyncState, Winner};
+    use std::cell::Cell;
+    use std::fs;
+    use std::path;
+
+    struct Sides {
+        root: path::PathBuf,
+        op1: path::PathBuf,
+        archive: path::PathBuf,
+    }
+
+    impl Sides {
+        fn new(name: &str) -> Sides {
+            let root = std::env::temp_dir().join(format!("save-op1-sync-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&root);
+            Sides {
+                op1: root.join("op1"),
+                archive: root.join("archive"),
+                root,
+            }
+        }
+
+        fn put(side: &path::Path, key: &str, contents: &str) {
+            let file_path = side.join(key);
+            fs::create_dir_all(file_path.parent().unwrap()).unwrap();
+            fs::write(file_path, contents).unwrap();
+        }
+
+        fn get(side: &path::Path, key: &str) -> Option<String> {
+            fs::read_to_string(side.join(key)).ok()
+        }
+
+        fn sync(&self, state: &mut SyncState, winner: fn() -> Winner) -> Vec<String> {
+            sync(&self.op1, &self.archive, state, |_| winner()).unwrap()
+        }
+    }
+
+    impl Drop for Sides {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    const PAD: &str = "synth/user/pad.aif";
+
+    #[test]
+    fn new_patches_go_in_the_archive_and_stay_off_the_op1() {
+        let sides = Sides::new("new");
+        let mut state = SyncState::default();
+        Sides::put(&sides.op1, PAD, "pad");
+        assert_eq!(sides.sync(&mut state, || Winner::Neither), vec![format!("op-1 -> archive {}", PAD)]);
+        assert_eq!(Sides::get(&sides.archive, PAD).as_deref(), Some("pad"));
+        assert!(!state.files[PAD].on_device);
+
+        // and then there's nothing to do
+        assert!(sides.sync(&mut state, || Winner::Neither).is_empty());
+
+        // a patch only ever in the archive doesn't go onto the op-1 either
+        Sides::put(&sides.archive, "drum/user/kit.aif", "kit");
+        assert!(sides.sync(&mut state, || Winner::Neither).is_empty());
+        assert!(sides.sync(&mut state, || Winner::Neither).is_empty());
+        assert_eq!(Sides::get(&sides.op1, "drum/user/kit.aif"), None);
+    }
+
+    #[test]
+    fn deletions_are_kept_and_mentioned_once() {
+        let sides = Sides::new("deleted");
+        let mut state = SyncState::default();
+        Sides::put(&sides.op1, PAD, "pad");
+        sides.sync(&mut state, || Winner::Neither);
+
+        fs::remove_file(sides.op1.join(PAD)).unwrap();
+        assert_eq!(
+            sides.sync(&mut state, || Winner::Neither),
+            vec![format!("deleted on the op-1, kept in the archive {}", PAD)]
+        );
+        assert!(sides.sync(&mut state, || Winner::Neither).is_empty());
+        assert_eq!(Sides::get(&sides.archive, PAD).as_deref(), Some("pad"));
+        assert_eq!(Sides::get(&sides.op1, PAD), None);
+    }
+
+    #[test]
+    fn marked_patches_go_back_on_the_op1() {
+        let sides = Sides::new("marked");
+        let mut state = SyncState::default();
+        Sides::put(&sides.op1, PAD, "pad");
+        sides.sync(&mut state, || Winner::Neither);
+        assert!(state.mark("synth/user/nothing.aif", true).is_err());
+        state.mark(PAD, true).unwrap();
+
+        fs::remove_file(sides.op1.join(PAD)).unwrap();
+        assert_eq!(sides.sync(&mut state, || Winner::Neither), vec![format!("archive -> op-1 {}", PAD)]);
+        assert_eq!(Sides::get(&sides.op1, PAD).as_deref(), Some("pad"));
+        assert!(state.files[PAD].on_device);
+    }
+
+    #[test]
+    fn changes_on_one_side_win() {
+        let sides = Sides::new("changed");
+        let mut state = SyncState::default();
+        Sides::put(&sides.op1, PAD, "pad");
+        sides.sync(&mut state, || Winner::Neither);
+
+        Sides::put(&sides.op1, PAD, "brighter pad");
+        assert_eq!(sides.sync(&mut state, || Winner::Neither), vec![format!("op-1 -> archive {}", PAD)]);
+        assert_eq!(Sides::get(&sides.archive, PAD).as_deref(), Some("brighter pad"));
+
+        Sides::put(&sides.archive, PAD, "darker pad");
+        assert_eq!(sides.sync(&mut state, || Winner::Neither), vec![format!("archive -> op-1 {}", PAD)]);
+        assert_eq!(Sides::get(&sides.op1, PAD).as_deref(), Some("darker pad"));
+    }
+
+    #[test]
+    fn conflicts_are_resolved_or_left_alone() {
+        let sides = Sides::new("conflict");
+        let mut state = SyncState::default();
+        Sides::put(&sides.op1, PAD, "pad");
+        sides.sync(&mut state, || Winner::Neither);
+
+        Sides::put(&sides.op1, PAD, "device pad");
+        Sides::put(&sides.archive, PAD, "archive pad");
+        let asked = Cell::new(0);
+        let log = sync(&sides.op1, &sides.archive, &mut state, |conflict: &Conflict| {
+            assert_eq!(conflict.key, PAD);
+            asked.set(asked.get() + 1);
+            Winner::Neither
+        })
+        .unwrap();
+        assert_eq!(asked.get(), 1);
+        assert_eq!(log, vec![format!("left alone {}", PAD)]);
+        assert_eq!(Sides::get(&sides.op1, PAD).as_deref(), Some("device pad"));
+        assert_eq!(Sides::get(&sides.archive, PAD).as_deref(), Some("archive pad"));
+
+        // still a conflict next time, until something wins
+        assert_eq!(sides.sync(&mut state, || Winner::Archive), vec![format!("archive -> op-1 {}", PAD)]);
+        assert_eq!(Sides::get(&sides.op1, PAD).as_deref(), Some("archive pad"));
+
+        Sides::put(&sides.op1, PAD, "device pad");
+        Sides::put(&sides.archive, PAD, "other pad");
+        assert_eq!(sides.sync(&mut state, || Winner::Device), vec![format!("op-1 -> archive {}", PAD)]);
+        assert_eq!(Sides::get(&sides.archive, PAD).as_deref(), Some("device pad"));
+        assert!(sides.sync(&mut state, || Winner::Neither).is_empty());
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seconds since 1970
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Year, month, day, hour, minute and second, in UTC
+pub fn civil(seconds: u64) -> (i64, u32, u32, u32, u32, u32) {
+    let days = (seconds / 86400) as i64;
+    let time = seconds % 86400;
+
+    // howard hinnant's days-to-civil
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (
+        year,
+        month,
+        day,
+        (time / 3600) as u32,
+        (time % 3600 / 60) as u32,
+        (time % 60) as u32,
+    )
+}
+
+/// A length like 3:07
+pub fn minutes(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Something like 2020-05-17-134512, which sorts in time order
+pub fn stamp(seconds: u64) -> String {
+    let (year, month, day, hour, minute, second) = civil(seconds);
+    format!(
+        "{}-{:02}-{:02}-{:02}{:02}{:02}",
+        year, month, day, hour, minute, second
+    )
+}
+
+/// Something like Sun, 17 May 2020 13:45:12 +0000, for rss
+pub fn rfc2822(seconds: u64) -> String {
+    const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let (year, month, day, hour, minute, second) = civil(seconds);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+        // 1970 started on a thursday
+        DAYS[(seconds / 86400 % 7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        hour,
+        minute,
+        second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{civil, minutes, rfc2822, stamp};
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil(0), (1970, 1, 1, 0, 0, 0));
+        assert_eq!(civil(86399), (1970, 1, 1, 23, 59, 59));
+        assert_eq!(civil(951_782_400), (2000, 2, 29, 0, 0, 0));
+        assert_eq!(civil(1_589_723_112), (2020, 5, 17, 13, 45, 12));
+        assert_eq!(civil(4_107_542_399), (2100, 2, 28, 23, 59, 59));
+        // 2100 isn't a leap year
+        assert_eq!(civil(4_107_542_400), (2100, 3, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn rfc2822_dates() {
+        assert_eq!(rfc2822(0), "Thu, 01 Jan 1970 00:00:00 +0000");
+        assert_eq!(rfc2822(1_589_723_112), "Sun, 17 May 2020 13:45:12 +0000");
+        assert_eq!(rfc2822(951_782_400), "Tue, 29 Feb 2000 00:00:00 +0000");
+    }
+
+    #[test]
+    fn stamps_and_minutes() {
+        assert_eq!(stamp(1_589_723_112), "2020-05-17-134512");
+        assert_eq!(minutes(0.0), "0:00");
+        assert_eq!(minutes(59.6), "1:00");
+        assert_eq!(minutes(187.0), "3:07");
+    }
+}
@@ -0,0 +1,556 @@
+use super::audio::{self, aiff, Audio};
+use super::copy::{self, Progress};
+use super::disk::Disk;
+use super::operator::{Operator, Patch, TapeMapping};
+use super::song::Song;
+use super::waveform;
+use ears::AudioController;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Style, Stylize};
+use ratatui::widgets::{Block, Gauge, List, ListState, Paragraph};
+use ratatui::Frame;
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind, Result};
+use std::path;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use std::time::Duration;
+
+const HELP: &str = "tab switch  ↑↓ move  s save  p preview  l load tape  u upload  S snapshot  r refresh  q quit
+patches, picking tracks, sync and the library are in save-op1 menu";
+
+// a finished job, with what to say about it
+type Done = std::result::Result<String, String>;
+
+#[derive(Clone, Copy)]
+enum DeviceItem {
+    Side(usize),
+    Track(usize),
+    Patch(usize),
+}
+
+// what the name being typed in is for, or the y/n being waited on
+enum Ask {
+    SaveSide(usize),
+    SaveTape,
+    LoadTape(String),
+}
+
+impl Ask {
+    fn prompt(&self) -> String {
+        match self {
+            Ask::LoadTape(slug) => format!("put {} over the tape on the op-1? y/n", slug),
+            _ => "name it, enter to save, esc to cancel".to_owned(),
+        }
+    }
+}
+
+struct App<'a> {
+    op1: &'a Operator,
+    disk: &'a Disk,
+    patches: Vec<Patch>,
+    songs: Vec<String>,
+    on_archive: bool,
+    device: ListState,
+    archive: ListState,
+    details: String,
+    // drawn in the background, since drawing one means reading the whole file
+    waveforms: HashMap<path::PathBuf, Option<String>>,
+    wanted: HashSet<path::PathBuf>,
+    draw_waveform: Sender<path::PathBuf>,
+    log: Vec<String>,
+    progress: Option<Progress>,
+    busy: bool,
+    input: Option<(Ask, String)>,
+    music: Option<ears::Music>,
+    quit: bool,
+}
+
+fn describe_audio(file_path: &path::Path) -> String {
+    let mut details = file_path.to_str().unwrap().to_owned();
+    if let Ok(info) = aiff::info(file_path) {
+        details.push_str(&format!(
+            "\n{:.1}s, {} channels, {} bit, {}Hz",
+            info.frames as f64 / f64::from(info.sample_rate.max(1)),
+            info.channels,
+            info.bits,
+            info.sample_rate
+        ));
+    }
+    details
+}
+
+impl<'a> App<'a> {
+    fn new(op1: &'a Operator, disk: &'a Disk, draw_waveform: Sender<path::PathBuf>) -> App<'a> {
+        let mut app = App {
+            op1,
+            disk,
+            patches: vec![],
+            songs: vec![],
+            on_archive: false,
+            device: ListState::default().with_selected(Some(0)),
+            archive: ListState::default().with_selected(Some(0)),
+            details: String::new(),
+            waveforms: HashMap::new(),
+            wanted: HashSet::new(),
+            draw_waveform,
+            log: vec![],
+            progress: None,
+            busy: false,
+            input: None,
+            music: None,
+            quit: false,
+        };
+        app.refresh();
+        app
+    }
+
+    fn refresh(&mut self) {
+        // saving and loading change what's in the files
+        self.waveforms.clear();
+        self.wanted.clear();
+        self.patches = self.op1.patches().unwrap_or_default();
+        self.songs = self.disk.list_songs().unwrap_or_default();
+        self.songs.sort();
+        self.describe();
+    }
+
+    fn say(&mut self, line: String) {
+        self.log.push(line);
+    }
+
+    fn device_items(&self) -> Vec<DeviceItem> {
+        let mut items = vec![DeviceItem::Side(0), DeviceItem::Side(1)];
+        items.extend((0..4).map(DeviceItem::Track));
+        items.extend((0..self.patches.len()).map(DeviceItem::Patch));
+        items
+    }
+
+    fn label(&self, item: DeviceItem) -> String {
+        match item {
+            DeviceItem::Side(index) => format!("album  {}", self.side(index)),
+            DeviceItem::Track(index) => format!("tape   {}", self.op1.tape.tracks()[index]),
+            DeviceItem::Patch(index) => {
+                let patch = &self.patches[index];
+                format!("{:<6} {}/{}", patch.engine(), patch.folder, patch.name())
+            }
+        }
+    }
+
+    fn side(&self, index: usize) -> &'a super::operator::Side {
+        if index == 0 {
+            &self.op1.album.side_a
+        } else {
+            &self.op1.album.side_b
+        }
+    }
+
+    fn selected_device(&self) -> Option<DeviceItem> {
+        self.device
+            .selected()
+            .and_then(|index| self.device_items().get(index).copied())
+    }
+
+    fn selected_song(&self) -> Option<String> {
+        self.archive
+            .selected()
+            .and_then(|index| self.songs.get(index).cloned())
+    }
+
+    // the audio behind whatever's selected, for previews
+    fn selected_path(&self) -> Option<path::PathBuf> {
+        if self.on_archive {
+            let slug = self.selected_song()?;
+            return self.disk.sources(&slug).into_iter().next().map(|(_, path)| path);
+        }
+        match self.selected_device()? {
+            DeviceItem::Side(index) => self.side(index).path().cloned(),
+            DeviceItem::Track(index) => Some(path::PathBuf::from(self.op1.tape.tracks()[index].path())),
+            DeviceItem::Patch(index) => Some(self.patches[index].path.to_owned()),
+        }
+    }
+
+    fn waveform(&mut self, file_path: path::PathBuf) -> String {
+        match self.waveforms.get(&file_path) {
+            Some(Some(waveform)) => format!("\n{}", waveform),
+            Some(None) => String::new(),
+            None => {
+                if self.wanted.insert(file_path.to_owned()) {
+                    let _ = self.draw_waveform.send(file_path);
+                }
+                "\ndrawing the waveform".to_owned()
+            }
+        }
+    }
+
+    fn describe(&mut self) {
+        self.details = if self.on_archive {
+            match self.selected_song() {
+                Some(slug) => {
+                    let metadata = self.disk.metadata(&slug).unwrap_or_default();
+                    let mut details = format!(
+                        "{} by {}",
+                        metadata.name.as_deref().unwrap_or(&slug),
+                        metadata.artist.as_deref().unwrap_or("?")
+                    );
+                    if let Some(album) = &metadata.album {
+                        details.push_str(&format!(", track {} of {}", metadata.track.unwrap_or(0), album));
+                    }
+                    if let Some(device) = &metadata.device {
+                        details.push_str(&format!("\nfrom {}", device));
+                    }
+                    if let Some(loudness) = metadata.loudness {
+                        details.push_str(&format!("\n{}", loudness));
+                    }
+                    if let Some((_, file_path)) = self.disk.sources(&slug).into_iter().next() {
+                        details.push_str(&self.waveform(file_path));
+                    }
+                    details
+                }
+                None => "no songs yet".to_owned(),
+            }
+        } else {
+            match self.selected_device() {
+                Some(DeviceItem::Patch(index)) => {
+                    let patch = &self.patches[index];
+                    format!("{}\n{}", patch.path.to_str().unwrap(), patch)
+                }
+                Some(_) => match self.selected_path() {
+                    Some(file_path) => describe_audio(&file_path) + &self.waveform(file_path),
+                    None => String::new(),
+                },
+                None => String::new(),
+            }
+        };
+    }
+
+    fn move_by(&mut self, step: isize) {
+        let (state, length) = if self.on_archive {
+            (&mut self.archive, self.songs.len())
+        } else {
+            let length = self.device_items().len();
+            (&mut self.device, length)
+        };
+        if length == 0 {
+            return;
+        }
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + step).rem_euclid(length as isize) as usize));
+        self.describe();
+    }
+
+    // same thing again stops it
+    fn preview(&mut self) {
+        if let Some(mut music) = self.music.take() {
+            if music.is_playing() {
+                music.stop();
+                self.say("stopped".to_owned());
+                return;
+            }
+        }
+        let file_path = match self.selected_path() {
+            Some(file_path) => file_path,
+            None => return,
+        };
+        match ears::Music::new(file_path.to_str().unwrap()) {
+            Ok(mut music) => {
+                music.play();
+                self.say(format!("playing {}", file_path.to_str().unwrap()));
+                self.music = Some(music);
+            }
+            Err(error) => self.say(format!("couldn't play that: {}", error)),
+        }
+    }
+
+    // off the main thread, so the screen keeps up with the copying
+    fn start<'scope>(
+        &mut self,
+        scope: &'scope thread::Scope<'scope, '_>,
+        done: &Sender<Done>,
+        what: String,
+        job: impl FnOnce() -> Result<String> + Send + 'scope,
+    ) {
+        if self.busy {
+            self.say("hang on, still busy".to_owned());
+            return;
+        }
+        self.busy = true;
+        self.say(what);
+        let done = done.clone();
+        scope.spawn(move || {
+            let _ = done.send(job().map_err(|error| error.to_string()));
+        });
+    }
+
+    fn finish(&mut self, done: Done) {
+        self.busy = false;
+        self.progress = None;
+        match done {
+            Ok(message) => self.say(message),
+            Err(error) => self.say(format!("oh no: {}", error)),
+        }
+        self.refresh();
+    }
+
+    fn answered<'scope>(
+        &mut self,
+        scope: &'scope thread::Scope<'scope, '_>,
+        done: &Sender<Done>,
+        ask: Ask,
+        name: String,
+    ) where
+        'a: 'scope,
+    {
+        let (op1, disk) = (self.op1, self.disk);
+        let artist = disk.config().map_or("quiet party".to_owned(), |config| config.artist);
+        match ask {
+            Ask::SaveSide(index) => {
+                let side = self.side(index);
+                self.start(scope, done, format!("saving {} as {}", side, name), move || {
+                    let side_path = side
+                        .path()
+                        .ok_or_else(|| Error::new(ErrorKind::NotFound, "that side isn't on the op-1"))?;
+                    let mut song = Song::new(disk, &name, &artist);
+                    song.device = Some(op1.device.to_owned());
+                    disk.save_song(&song, Some(side_path), vec![], None, None)?;
+                    Ok(format!("saved {}", song.slug))
+                })
+            }
+            Ask::SaveTape => self.start(scope, done, format!("saving the tape as {}", name), move || {
+                let mut song = Song::new(disk, &name, &artist);
+                song.device = Some(op1.device.to_owned());
+                let tracks = op1.tape.tracks().into_iter().filter(|track| !matches!(track.is_empty(), Ok(true))).collect();
+                disk.save_song(&song, None, tracks, None, None)?;
+                Ok(format!("saved the tape as {}", song.slug))
+            }),
+            Ask::LoadTape(slug) => self.start(scope, done, format!("loading {} onto the op-1", slug), move || {
+                let mut mapping: TapeMapping = Default::default();
+                for (track, source) in mapping.iter_mut().enumerate() {
+                    let track_path = disk.track_path(&slug, track + 1);
+                    // like the menu, an empty track leaves what's on the op-1 alone
+                    if track_path.exists() && !matches!(audio::is_silent(&track_path), Ok(true)) {
+                        *source = Some(track_path);
+                    }
+                }
+                op1.save_tape(&mapping)?;
+                let loaded = mapping.iter().filter(|source| source.is_some()).count();
+                Ok(format!("loaded {} tracks of {}", loaded, slug))
+            }),
+        }
+    }
+
+    fn typing(&mut self, code: KeyCode) -> Option<(Ask, String)> {
+        let (_, text) = self.input.as_mut()?;
+        match code {
+            KeyCode::Char(letter) => text.push(letter),
+            KeyCode::Backspace => {
+                text.pop();
+            }
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => {
+                let (ask, text) = self.input.take()?;
+                if !text.trim().is_empty() {
+                    return Some((ask, text.trim().to_owned()));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn key<'scope>(&mut self, code: KeyCode, scope: &'scope thread::Scope<'scope, '_>, done: &Sender<Done>)
+    where
+        'a: 'scope,
+    {
+        if let Some((Ask::LoadTape(_), _)) = &self.input {
+            let (ask, _) = self.input.take().unwrap();
+            if code == KeyCode::Char('y') {
+                self.answered(scope, done, ask, String::new());
+            } else {
+                self.say("left the tape alone".to_owned());
+            }
+            return;
+        }
+        if self.input.is_some() {
+            if let Some((ask, name)) = self.typing(code) {
+                self.answered(scope, done, ask, name);
+            }
+            return;
+        }
+
+        let (op1, disk) = (self.op1, self.disk);
+        match code {
+            // quitting would wait for the job with the screen in a state
+            KeyCode::Char('q') | KeyCode::Esc if self.busy => {
+                self.say("still busy, quit once it's finished".to_owned())
+            }
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab | KeyCode::Left | KeyCode::Right => {
+                self.on_archive = !self.on_archive;
+                self.describe();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Char('r') => self.refresh(),
+            KeyCode::Char('p') => self.preview(),
+            KeyCode::Char('s') if !self.on_archive => match self.selected_device() {
+                Some(DeviceItem::Side(index)) => self.input = Some((Ask::SaveSide(index), String::new())),
+                Some(DeviceItem::Track(_)) => self.input = Some((Ask::SaveTape, String::new())),
+                _ => self.say("patches are saved with snapshots, that's S, and loaded from save-op1 menu".to_owned()),
+            },
+            KeyCode::Char('S') => self.start(scope, done, "taking a snapshot".to_owned(), move || {
+                Ok(format!("saved snapshot {}", disk.snapshot(op1.path(), &op1.device)?))
+            }),
+            KeyCode::Char('l') if self.on_archive => {
+                let slug = match self.selected_song() {
+                    Some(slug) => slug,
+                    None => return,
+                };
+                let saved: Vec<String> = (1..=4)
+                    .filter(|number| disk.track_path(&slug, *number).exists())
+                    .map(|number| format!("track_{}", number))
+                    .collect();
+                if saved.is_empty() {
+                    self.say(format!("{} has no tape", slug));
+                    return;
+                }
+                let tracks = format!("{} saved, empty ones get skipped", saved.join(", "));
+                self.input = Some((Ask::LoadTape(slug), tracks));
+            }
+            KeyCode::Char('u') if self.on_archive => {
+                let slug = match self.selected_song() {
+                    Some(slug) => slug,
+                    None => return,
+                };
+                self.start(scope, done, format!("uploading {}", slug), move || {
+                    disk.upload_mp3(&disk.song(&slug)?)?;
+                    Ok(format!("uploaded {}", slug))
+                })
+            }
+            _ => {}
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [top, details, progress, log, help] = Layout::vertical([
+        Constraint::Min(8),
+        Constraint::Length(10),
+        Constraint::Length(3),
+        Constraint::Length(6),
+        Constraint::Length(2),
+    ])
+    .areas(frame.area());
+    let [left, right] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(top);
+
+    let pane = |title: String, focused: bool| {
+        let block = Block::bordered().title(title);
+        if focused {
+            block.border_style(Style::new().yellow())
+        } else {
+            block
+        }
+    };
+
+    let labels: Vec<String> = app.device_items().into_iter().map(|item| app.label(item)).collect();
+    let device = List::new(labels)
+        .block(pane(format!("{} ({})", app.op1.device, app.op1.layout.model()), !app.on_archive))
+        .highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(device, left, &mut app.device);
+
+    let archive = List::new(app.songs.clone())
+        .block(pane("archive".to_owned(), app.on_archive))
+        .highlight_style(Style::new().reversed());
+    frame.render_stateful_widget(archive, right, &mut app.archive);
+
+    let details_widget = match &app.input {
+        Some((ask @ Ask::LoadTape(_), text)) => Paragraph::new(text.as_str()).block(pane(ask.prompt(), true)),
+        Some((ask, text)) => Paragraph::new(format!("{}_", text)).block(pane(ask.prompt(), true)),
+        None => Paragraph::new(app.details.as_str()).block(Block::bordered()),
+    };
+    frame.render_widget(details_widget, details);
+
+    let gauge = match &app.progress {
+        Some(progress) => Gauge::default()
+            .ratio((progress.done as f64 / progress.total.max(1) as f64).min(1.0))
+            .label(format!("{} {}/{}", progress.name, progress.done, progress.total)),
+        None if app.busy => Gauge::default().ratio(0.0).label("working"),
+        None => Gauge::default().ratio(0.0).label(""),
+    };
+    frame.render_widget(gauge.block(Block::bordered().title("copying")), progress);
+
+    let lines = app.log.len().saturating_sub(usize::from(log.height.saturating_sub(2)));
+    frame.render_widget(
+        Paragraph::new(app.log[lines..].join("\n")).block(Block::bordered()),
+        log,
+    );
+    frame.render_widget(Paragraph::new(HELP).dim(), help);
+}
+
+struct Restore;
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
+
+/// Saving and loading sides and tapes on one screen: the op-1 on the left, the
+/// archive on the right, and whatever's copying along the bottom. Patches,
+/// picking tracks, sync and the patch library are in `save-op1 menu` and
+/// the commands, not here.
+pub fn run(op1: &Operator, disk: &Disk) -> Result<()> {
+    let (progress_sender, progress) = channel();
+    copy::report_progress(Some(progress_sender));
+    let (message_sender, messages) = channel();
+    copy::report_messages(Some(message_sender));
+    let mut terminal = ratatui::init();
+
+    let result = thread::scope(|scope| -> Result<()> {
+        let (done_sender, done) = channel();
+        let (waveform_sender, waveform_requests) = channel::<path::PathBuf>();
+        let (drawn_sender, drawn) = channel();
+        scope.spawn(move || {
+            for file_path in waveform_requests {
+                let waveform = Audio::read(&file_path).ok().map(|audio| waveform::ascii(&audio, 64, 6));
+                if drawn_sender.send((file_path, waveform)).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut app = App::new(op1, disk, waveform_sender);
+        // however this ends, the terminal goes back before anything still
+        // running is waited for
+        let _restore = Restore;
+        while !app.quit {
+            terminal.draw(|frame| draw(frame, &mut app))?;
+            if event::poll(Duration::from_millis(100))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press {
+                        app.key(key.code, scope, &done_sender);
+                    }
+                }
+            }
+            while let Ok((file_path, waveform)) = drawn.try_recv() {
+                app.wanted.remove(&file_path);
+                app.waveforms.insert(file_path, waveform);
+                app.describe();
+            }
+            while let Ok(latest) = progress.try_recv() {
+                app.progress = Some(latest);
+            }
+            while let Ok(line) = messages.try_recv() {
+                app.say(line);
+            }
+            while let Ok(finished) = done.try_recv() {
+                app.finish(finished);
+            }
+        }
+        Ok(())
+    });
+
+    copy::report_progress(None);
+    copy::report_messages(None);
+    result
+}
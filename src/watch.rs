@@ -0,0 +1,171 @@
+use super::audio;
+use super::config::{Config, WatchAction};
+use super::disk::Disk;
+use super::hooks::notify;
+use super::operator::Operator;
+use super::song::Song;
+use super::time;
+use std::collections::HashSet;
+use std::fs::read_dir;
+use std::io::Result;
+use std::path;
+use std::thread::sleep;
+use std::time::Duration;
+
+fn is_op1(dir_path: &path::Path) -> bool {
+    Operator::check_structure(dir_path).unwrap_or(false)
+}
+
+// the mount itself, or anything up to two levels down, since usb drives turn up
+// in /media/<user>/<label>
+fn find(dir_path: &path::PathBuf, depth: usize, found: &mut HashSet<path::PathBuf>) {
+    if is_op1(dir_path) {
+        found.insert(dir_path.to_owned());
+        return;
+    }
+    if depth == 0 {
+        return;
+    }
+    if let Ok(entries) = read_dir(dir_path) {
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                find(&entry.path(), depth - 1, found);
+            }
+        }
+    }
+}
+
+fn save_album(disk: &Disk, config: &Config, op1: &Operator) -> Result<Vec<String>> {
+    let stamp = time::stamp(time::now());
+    let mut saved = vec![];
+    for side in &[&op1.album.side_a, &op1.album.side_b] {
+        let side_path = match side.path() {
+            Some(side_path) => side_path,
+            None => continue,
+        };
+        match audio::is_silent(side_path) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(error) => disk.log(&format!("couldn't check if {} is empty ({}), saving it anyway", side, error))?,
+        }
+        let mut song = Song::new(disk, &format!("{} {}", side, stamp), &config.artist);
+        song.device = Some(op1.device.to_owned());
+        disk.save_aif(&song, side_path)?;
+        saved.push(song.slug);
+    }
+    Ok(saved)
+}
+
+fn act(disk: &Disk, config: &Config, op1_path: &path::PathBuf) -> Result<()> {
+    let op1 = Operator::new(op1_path)?;
+    for action in &config.watch.actions {
+        match action {
+            WatchAction::Snapshot => {
+                let name = disk.snapshot(op1.path(), &op1.device)?;
+                disk.log(&format!("saved snapshot {} of {}", name, op1.device))?;
+            }
+            WatchAction::SaveAlbum => {
+                let saved = save_album(disk, config, &op1)?;
+                if saved.is_empty() {
+                    disk.log("both album sides were silent, so nothing saved")?;
+                }
+                for slug in saved {
+                    disk.log(&format!("saved album side as {}", slug))?;
+                }
+            }
+            WatchAction::Notify => {
+                notify(
+                    "save-op1",
+                    &format!(
+                        "found {}, an {}, at {}",
+                        op1.device,
+                        op1.layout.model(),
+                        op1_path.to_str().unwrap()
+                    ),
+                );
+                disk.log("sent a notification")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Look through the mounts once, and say which op-1s turned up and which went
+/// away since `present`, which is updated to match
+pub fn poll(
+    mounts: &[path::PathBuf],
+    present: &mut HashSet<path::PathBuf>,
+) -> (Vec<path::PathBuf>, Vec<path::PathBuf>) {
+    let mut found = HashSet::new();
+    for mount in mounts {
+        find(mount, 2, &mut found);
+    }
+    let mut added: Vec<path::PathBuf> = found.difference(present).cloned().collect();
+    let mut removed: Vec<path::PathBuf> = present.difference(&found).cloned().collect();
+    added.sort();
+    removed.sort();
+    *present = found;
+    (added, removed)
+}
+
+/// Wait for an op-1 to be plugged in, do the configured things to it, and
+/// wait for the next one. Runs until it's killed.
+pub fn watch(disk: &Disk, config: &Config) -> Result<()> {
+    let mut present = HashSet::new();
+    disk.log(&format!("watching {:?}", config.watch.mounts))?;
+    loop {
+        let (added, removed) = poll(&config.watch.mounts, &mut present);
+        for op1_path in &added {
+            disk.log(&format!("op-1 plugged in at {}", op1_path.to_str().unwrap()))?;
+            // one bad action shouldn't stop the watching
+            if let Err(error) = act(disk, config, op1_path) {
+                disk.log(&format!("that went wrong: {}", error))?;
+            }
+        }
+        for op1_path in &removed {
+            disk.log(&format!("op-1 unplugged from {}", op1_path.to_str().unwrap()))?;
+        }
+
+        sleep(Duration::from_secs(config.watch.interval.max(1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::poll;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path;
+
+    fn copy_dir(source: &path::Path, target: &path::Path) {
+        fs::create_dir_all(target).unwrap();
+        for entry in fs::read_dir(source).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &target.join(entry.file_name()));
+            } else {
+                fs::copy(entry.path(), target.join(entry.file_name())).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn notices_the_fake_op1_coming_and_going() {
+        let media = std::env::temp_dir().join(format!("save-op1-watch-{}", std::process::id()));
+        let mount = media.join("chee").join("54FF-1FEE");
+        fs::create_dir_all(media.join("chee")).unwrap();
+        let mounts = vec![media.to_owned()];
+        let mut present = HashSet::new();
+
+        assert_eq!(poll(&mounts, &mut present), (vec![], vec![]));
+
+        copy_dir(&path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fake-op1"), &mount);
+        assert_eq!(poll(&mounts, &mut present), (vec![mount.to_owned()], vec![]));
+        assert_eq!(poll(&mounts, &mut present), (vec![], vec![]));
+
+        fs::remove_dir_all(&mount).unwrap();
+        assert_eq!(poll(&mounts, &mut present), (vec![], vec![mount.to_owned()]));
+
+        fs::remove_dir_all(&media).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use super::audio::Audio;
+use std::f32::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Result};
+use std::path;
+
+const SIZE: usize = 1000;
+const FFT_SIZE: usize = 1024;
+const BACKGROUND: [u8; 3] = [0x10, 0x10, 0x18];
+const FOREGROUND: [u8; 3] = [0xf0, 0xe8, 0xd8];
+
+/// The lowest and highest sample in each of `columns` equal slices
+fn peaks(samples: &[f32], columns: usize) -> Vec<(f32, f32)> {
+    let per_column = (samples.len() / columns.max(1)).max(1);
+    (0..columns)
+        .map(|column| {
+            let start = (column * per_column).min(samples.len());
+            let end = (start + per_column).min(samples.len());
+            samples[start..end]
+                .iter()
+                .fold((0.0, 0.0), |(low, high), sample| {
+                    (sample.min(low), sample.max(high))
+                })
+        })
+        .collect()
+}
+
+fn write_png(file_path: &path::Path, width: usize, height: usize, pixels: &[u8]) -> Result<()> {
+    let file = BufWriter::new(File::create(file_path)?);
+    let mut encoder = png::Encoder::new(file, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)?;
+    Ok(())
+}
+
+/// A square picture of the whole waveform, big enough to use as cover art
+pub fn draw(audio: &Audio, file_path: &path::Path) -> Result<()> {
+    let mut pixels = BACKGROUND.repeat(SIZE * SIZE);
+    let middle = SIZE as f32 / 2.0;
+    for (x, (low, high)) in peaks(&audio.mono(), SIZE).into_iter().enumerate() {
+        let top = (middle - high * middle).max(0.0) as usize;
+        let bottom = ((middle - low * middle) as usize).min(SIZE - 1);
+        for y in top..=bottom {
+            let pixel = (y * SIZE + x) * 3;
+            pixels[pixel..pixel + 3].copy_from_slice(&FOREGROUND);
+        }
+    }
+    write_png(file_path, SIZE, SIZE, &pixels)
+}
+
+// plain recursive radix-2, the input length must be a power of two
+fn fft(real: &[f32], imaginary: &[f32]) -> (Vec<f32>, Vec<f32>) {
+    let length = real.len();
+    if length == 1 {
+        return (real.to_vec(), imaginary.to_vec());
+    }
+    let split = |offset: usize, values: &[f32]| -> Vec<f32> {
+        values.iter().skip(offset).step_by(2).copied().collect()
+    };
+    let (even_real, even_imaginary) = fft(&split(0, real), &split(0, imaginary));
+    let (odd_real, odd_imaginary) = fft(&split(1, real), &split(1, imaginary));
+
+    let mut out_real = vec![0.0; length];
+    let mut out_imaginary = vec![0.0; length];
+    for k in 0..length / 2 {
+        let angle = -2.0 * PI * k as f32 / length as f32;
+        let (sin, cos) = angle.sin_cos();
+        let twiddle_real = cos * odd_real[k] - sin * odd_imaginary[k];
+        let twiddle_imaginary = cos * odd_imaginary[k] + sin * odd_real[k];
+        out_real[k] = even_real[k] + twiddle_real;
+        out_imaginary[k] = even_imaginary[k] + twiddle_imaginary;
+        out_real[k + length / 2] = even_real[k] - twiddle_real;
+        out_imaginary[k + length / 2] = even_imaginary[k] - twiddle_imaginary;
+    }
+    (out_real, out_imaginary)
+}
+
+/// Time along the bottom, frequency up the side, louder is brighter
+pub fn draw_spectrogram(audio: &Audio, file_path: &path::Path) -> Result<()> {
+    let samples = audio.mono();
+    let bins = FFT_SIZE / 2;
+    let hop = (samples.len().saturating_sub(FFT_SIZE) / SIZE).max(1);
+    let window: Vec<f32> = (0..FFT_SIZE)
+        .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / FFT_SIZE as f32).cos())
+        .collect();
+
+    let mut pixels = BACKGROUND.repeat(SIZE * SIZE);
+    for x in 0..SIZE {
+        let start = x * hop;
+        let real: Vec<f32> = (0..FFT_SIZE)
+            .map(|n| samples.get(start + n).copied().unwrap_or(0.0) * window[n])
+            .collect();
+        let (real, imaginary) = fft(&real, &[0.0; FFT_SIZE]);
+        for y in 0..SIZE {
+            // log scale, so the bottom of the picture isn't all bass
+            let height = (SIZE - y) as f32 / SIZE as f32;
+            let bin = ((bins as f32).powf(height) as usize).min(bins - 1);
+            let magnitude = (real[bin].powi(2) + imaginary[bin].powi(2)).sqrt() / bins as f32;
+            // -90dB to 0dB across the whole range
+            let level = ((20.0 * magnitude.max(1e-9).log10() + 90.0) / 90.0).clamp(0.0, 1.0);
+            let pixel = (y * SIZE + x) * 3;
+            for channel in 0..3 {
+                let from = f32::from(BACKGROUND[channel]);
+                let to = f32::from(FOREGROUND[channel]);
+                pixels[pixel + channel] = (from + (to - from) * level) as u8;
+            }
+        }
+    }
+    write_png(file_path, SIZE, SIZE, &pixels)
+}
+
+/// A little waveform for the terminal, `height` should be even
+pub fn ascii(audio: &Audio, width: usize, height: usize) -> String {
+    let middle = height as f32 / 2.0;
+    let columns = peaks(&audio.mono(), width);
+    let mut lines = vec![];
+    for row in 0..height {
+        let line: String = columns
+            .iter()
+            .map(|(low, high)| {
+                let top = middle - high * middle;
+                let bottom = middle - low * middle;
+                let row = row as f32 + 0.5;
+                if row >= top && row <= bottom {
+                    '█'
+                } else if (row - middle).abs() < 0.5 {
+                    '─'
+                } else {
+                    ' '
+                }
+            })
+            .collect();
+        lines.push(line);
+    }
+    lines.join("\n")
+}